@@ -0,0 +1,53 @@
+import { generateKeyPairSync } from "node:crypto";
+import { Algorithm, JwtClient, JwtCacheClient, decodeHeader, decodeUnverified } from "../index";
+import test from "ava";
+import * as jose from "jose";
+
+const secret = "testsecretkeycanbeexposed";
+const client = new JwtClient(secret, { header: { keyId: "decode-kid" } });
+
+const testPayload = { user: "test@carbonteq.dev" };
+
+test("decodeHeader should return the token header", (t) => {
+	const token = client.sign(testPayload, 10000);
+
+	t.is(decodeHeader(token).keyId, "decode-kid");
+});
+
+test("decodeUnverified should return payload without checking signature", (t) => {
+	const token = client.sign(testPayload, 10000, { iss: "carbonteq" });
+	const otherClient = new JwtClient("someothersecret");
+
+	const { header, payload } = decodeUnverified(token);
+	t.is(header.keyId, "decode-kid");
+	t.deepEqual(payload.data, testPayload);
+	t.is(payload.iss, "carbonteq");
+	t.throws(() => otherClient.verify(token));
+});
+
+test("decodeUnverified should not populate the cache", (t) => {
+	const cacheClient = new JwtCacheClient(secret, 100, 10);
+	const token = new JwtClient("someothersecret").sign(testPayload, 10000);
+
+	decodeUnverified(token);
+	t.throws(() => cacheClient.verify(token));
+});
+
+test("decodeHeader and verifyComplete should return an embedded jwk", async (t) => {
+	const { publicKey, privateKey } = generateKeyPairSync("ec", { namedCurve: "P-256" });
+	const { x, y } = publicKey.export({ format: "jwk" });
+	const token = await new jose.SignJWT({ data: testPayload })
+		.setProtectedHeader({ alg: "ES256", jwk: { kty: "EC", crv: "P-256", x, y } })
+		.setExpirationTime("1h")
+		.sign(privateKey);
+	const expected = { keyType: "EC", curve: "P-256", x, y };
+
+	t.like(decodeHeader(token).jwk, expected);
+
+	const ecClient = JwtClient.withPubPrivKeys(
+		publicKey.export({ type: "spki", format: "pem" }),
+		privateKey.export({ type: "pkcs8", format: "pem" }),
+		{ header: { algorithm: Algorithm.ES256 } },
+	);
+	t.like(ecClient.verifyComplete(token).header.jwk, expected);
+});
@@ -0,0 +1,23 @@
+import { createHmac, generateKeyPairSync } from "node:crypto";
+import { Algorithm, JwtClient } from "../index";
+
+/** Base64url encoded `{"alg":"HS256"}` header */
+export const hs256Header = Buffer.from(JSON.stringify({ alg: "HS256" })).toString("base64url");
+
+/**
+ * RS256 client that also accepts HS256, and a way to forge HS256 signatures keyed with its public
+ * key, as an attacker knowing only that key would
+ */
+export const forgedHmacFixture = () => {
+	const rsa = generateKeyPairSync("rsa", { modulusLength: 2048 });
+	const rsClient = JwtClient.withPubPrivKeys(
+		rsa.publicKey.export({ type: "spki", format: "pem" }),
+		rsa.privateKey.export({ type: "pkcs8", format: "pem" }),
+		{ header: { algorithm: Algorithm.RS256 }, validation: { algorithms: [Algorithm.RS256, Algorithm.HS256] } },
+	);
+	const publicKey = rsa.publicKey.export({ type: "pkcs1", format: "der" });
+	const forgeSignature = (signingInput: string) =>
+		createHmac("sha256", publicKey).update(signingInput).digest("base64url");
+
+	return { rsClient, forgeSignature };
+};
@@ -0,0 +1,122 @@
+import { generateKeyPairSync } from "node:crypto";
+import { ErrorCode, JweClient, JwtClient, KeyManagement } from "../index";
+import test from "ava";
+
+const secret = Buffer.alloc(32, "k");
+const normalExpiresIn = 10000;
+const testPayload = { email: "test@carbonteq.dev" };
+
+const rsa = generateKeyPairSync("rsa", { modulusLength: 2048 });
+const ec = generateKeyPairSync("ec", { namedCurve: "P-256" });
+const pem = (keys: typeof rsa) => ({
+	pub: keys.publicKey.export({ type: "spki", format: "pem" }),
+	priv: keys.privateKey.export({ type: "pkcs8", format: "pem" }),
+});
+
+const clients: Array<[KeyManagement, JweClient]> = [
+	[KeyManagement.Dir, new JweClient(secret)],
+	[KeyManagement.A256KW, new JweClient(secret, { keyManagement: KeyManagement.A256KW })],
+	[KeyManagement.RsaOaep256, JweClient.withPubPrivKeys(pem(rsa).pub, pem(rsa).priv)],
+	[
+		KeyManagement.EcdhEs,
+		JweClient.withPubPrivKeys(pem(ec).pub, pem(ec).priv, { keyManagement: KeyManagement.EcdhEs }),
+	],
+];
+
+for (const [keyManagement, client] of clients) {
+	test(`${keyManagement} should round trip the claims`, (t) => {
+		const token = client.encrypt(testPayload, normalExpiresIn, { sub: "user-1" });
+		const header = JSON.parse(Buffer.from(token.split(".")[0], "base64url").toString());
+
+		t.is(token.split(".").length, 5);
+		t.is(header.alg, keyManagement);
+		t.is(header.enc, "A256GCM");
+
+		const claims = client.decrypt(token);
+		t.deepEqual(claims.data, testPayload);
+		t.is(claims.sub, "user-1");
+	});
+}
+
+test("tampered tokens should fail to decrypt", (t) => {
+	const client = new JweClient(secret);
+	const parts = client.encrypt(testPayload, normalExpiresIn).split(".");
+	parts[3] = Buffer.from("tampered").toString("base64url");
+
+	const err = t.throws<Error & { code: string }>(() => client.decrypt(parts.join(".")));
+	t.is(err?.code, ErrorCode.DecryptionFailed);
+});
+
+test("truncated authentication tags should be rejected", (t) => {
+	const client = new JweClient(secret);
+	const parts = client.encrypt(testPayload, normalExpiresIn).split(".");
+	parts[4] = parts[4].slice(0, 4);
+
+	const err = t.throws<Error & { code: string }>(() => client.decrypt(parts.join(".")));
+	t.is(err?.code, ErrorCode.Malformed);
+});
+
+test("decrypted claims should be validated", (t) => {
+	const client = new JweClient(secret, { validation: { iss: ["carbonteq"] }, clock: { offset: -20000 } });
+
+	const expired = t.throws<Error & { code: string }>(() =>
+		new JweClient(secret).decrypt(client.encrypt(testPayload, normalExpiresIn, { iss: "carbonteq" })),
+	);
+	t.is(expired?.code, ErrorCode.Expired);
+
+	const wrongIssuer = t.throws<Error & { code: string }>(() =>
+		client.decrypt(client.encrypt(testPayload, normalExpiresIn, { iss: "other" })),
+	);
+	t.is(wrongIssuer?.code, ErrorCode.InvalidIssuer);
+});
+
+test("tokens should only decrypt with the configured key management", (t) => {
+	const token = new JweClient(secret).encrypt(testPayload, normalExpiresIn);
+
+	const err = t.throws<Error & { code: string }>(() =>
+		new JweClient(secret, { keyManagement: KeyManagement.A256KW }).decrypt(token),
+	);
+	t.is(err?.code, ErrorCode.InvalidAlgorithm);
+	t.throws(() => new JweClient("too short"), { code: ErrorCode.InvalidKey });
+});
+
+test("nested tokens should be signed then encrypted", (t) => {
+	const jwe = clients[2][1];
+	const jwt = new JwtClient("testsecretkeycanbeexposed", { validation: { aud: ["api"] } });
+
+	const token = jwe.signAndEncrypt(jwt, testPayload, normalExpiresIn, { aud: "api" });
+	const header = JSON.parse(Buffer.from(token.split(".")[0], "base64url").toString());
+	t.is(header.cty, "JWT");
+
+	t.deepEqual(jwe.decryptAndVerify(jwt, token).data, testPayload);
+
+	const wrongSigner = new JwtClient("anothersecret", { validation: { aud: ["api"] } });
+	t.throws(() => jwe.decryptAndVerify(wrongSigner, token), { code: ErrorCode.InvalidSignature });
+	t.throws(() => jwe.decryptAndVerify(jwt, token, { aud: ["web"] }), { code: ErrorCode.InvalidAudience });
+});
+
+test("nested tokens should keep the signer's content type on the signed token", (t) => {
+	const jwe = new JweClient(secret);
+	const jwt = new JwtClient("testsecretkeycanbeexposed", { header: { contentType: "application/custom" } });
+	const decodeHeader = (token: string) =>
+		JSON.parse(Buffer.from(token.split(".")[0], "base64url").toString());
+
+	const token = jwe.signAndEncrypt(jwt, testPayload, normalExpiresIn);
+	t.is(decodeHeader(token).cty, "JWT");
+	t.deepEqual(jwe.decryptAndVerify(jwt, token).data, testPayload);
+
+	const nested = jwe.signAndEncrypt(new JwtClient("testsecretkeycanbeexposed"), testPayload, normalExpiresIn);
+	t.is(decodeHeader(nested).cty, "JWT");
+});
+
+test("nested and plain tokens should not be mixed up", (t) => {
+	const jwe = new JweClient(secret);
+	const jwt = new JwtClient("testsecretkeycanbeexposed");
+
+	t.throws(() => jwe.decrypt(jwe.signAndEncrypt(jwt, testPayload, normalExpiresIn)), {
+		code: ErrorCode.Malformed,
+	});
+	t.throws(() => jwe.decryptAndVerify(jwt, jwe.encrypt(testPayload, normalExpiresIn)), {
+		code: ErrorCode.Malformed,
+	});
+});
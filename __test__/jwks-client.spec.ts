@@ -0,0 +1,61 @@
+import { generateKeyPairSync } from "node:crypto";
+import { JwksClient, JwtClient, Algorithm } from "../index";
+import test from "ava";
+
+const { publicKey, privateKey } = generateKeyPairSync("rsa", {
+	modulusLength: 2048,
+});
+const pubPem = publicKey.export({ type: "spki", format: "pem" });
+const privPem = privateKey.export({ type: "pkcs8", format: "pem" });
+
+const jwk = { ...publicKey.export({ format: "jwk" }), kid: "rsa-1", alg: "RS256" };
+const jwks = new JwksClient(JSON.stringify({ keys: [jwk] }));
+
+const testPayload = { user: "test@carbonteq.dev" };
+
+const signerFor = (kid: string) =>
+	JwtClient.withPubPrivKeys(pubPem, privPem, {
+		header: { algorithm: Algorithm.RS256, keyId: kid },
+	});
+
+test("should verify token signed with a key from the set", (t) => {
+	const token = signerFor("rsa-1").sign(testPayload, 10000);
+
+	t.deepEqual(jwks.verify(token).data, testPayload);
+});
+
+test("should throw for unknown kid", (t) => {
+	const token = signerFor("rsa-2").sign(testPayload, 10000);
+
+	t.throws(() => jwks.verify(token), { message: /rsa-2/ });
+});
+
+test("should reject algorithm not allowed by the key", (t) => {
+	const client = new JwtClient("secret", { header: { keyId: "rsa-1" } });
+	const token = client.sign(testPayload, 10000);
+
+	t.throws(() => jwks.verify(token));
+});
+
+test("should skip and report unusable keys", (t) => {
+	const client = new JwksClient(
+		JSON.stringify({
+			keys: [jwk, { kty: "RSA", kid: "broken", n: "AQAB" }, { ...jwk, kid: "enc-1", use: "enc", alg: undefined }],
+		}),
+	);
+
+	t.deepEqual(client.keyIds, ["rsa-1"]);
+	t.deepEqual(
+		client.skippedKeys.map((key) => key.kid),
+		["broken", "enc-1"],
+	);
+	t.deepEqual(client.verify(signerFor("rsa-1").sign(testPayload, 10000)).data, testPayload);
+});
+
+test("should try every key sharing a kid", (t) => {
+	const other = generateKeyPairSync("rsa", { modulusLength: 2048 });
+	const otherJwk = { ...other.publicKey.export({ format: "jwk" }), kid: "rsa-1", alg: "RS256" };
+	const client = new JwksClient(JSON.stringify({ keys: [otherJwk, jwk] }));
+
+	t.deepEqual(client.verify(signerFor("rsa-1").sign(testPayload, 10000)).data, testPayload);
+});
@@ -0,0 +1,89 @@
+import { generateKeyPairSync } from "node:crypto";
+import {
+	Algorithm,
+	ErrorCode,
+	JwtClient,
+	signFlattenedJws,
+	signGeneralJws,
+	verifyFlattenedJws,
+	verifyGeneralJws,
+} from "../index";
+import test from "ava";
+import * as jose from "jose";
+import { forgedHmacFixture, hs256Header } from "./forged-hmac";
+
+const secret = "testsecretkeycanbeexposed";
+const payload = JSON.stringify({ amount: 100, currency: "USD" });
+
+const rsa = generateKeyPairSync("rsa", { modulusLength: 2048 });
+const hsClient = new JwtClient(secret, { header: { keyId: "hs" } });
+const rsClient = JwtClient.withPubPrivKeys(
+	rsa.publicKey.export({ type: "spki", format: "pem" }),
+	rsa.privateKey.export({ type: "pkcs8", format: "pem" }),
+	{ header: { algorithm: Algorithm.RS256, keyId: "rs" } },
+);
+
+test("general JWS should be verifiable with each signer's key", async (t) => {
+	const jws = signGeneralJws(payload, [
+		{ client: hsClient, header: { contentType: "json" } },
+		{ client: rsClient },
+	]);
+
+	t.is(jws.signatures.length, 2);
+	t.deepEqual(jws.signatures[0].header, { cty: "json" });
+
+	const joseRes = await jose.generalVerify(jws, rsa.publicKey);
+	t.is(Buffer.from(joseRes.payload).toString(), payload);
+	t.is(joseRes.protectedHeader?.kid, "rs");
+
+	const verified = verifyGeneralJws(jws, [rsClient, hsClient]);
+	t.is(verified.payload.toString(), payload);
+	t.is(verified.headers[0].keyId, "rs");
+	t.is(verified.headers[1].contentType, "json");
+});
+
+test("general JWS should fail if a verifier matches no signature", (t) => {
+	const jws = signGeneralJws(payload, [{ client: rsClient }]);
+
+	const err = t.throws(() => verifyGeneralJws(jws, [rsClient, hsClient]));
+	t.is(err?.code, ErrorCode.InvalidAlgorithm);
+});
+
+test("flattened JWS should reject a tampered payload", async (t) => {
+	const jws = signFlattenedJws(Buffer.from(payload), {
+		client: hsClient,
+		protected: { contentType: "json" },
+	});
+
+	const joseRes = await jose.flattenedVerify(jws, new TextEncoder().encode(secret));
+	t.is(joseRes.protectedHeader?.cty, "json");
+
+	t.is(verifyFlattenedJws(jws, hsClient).payload.toString(), payload);
+
+	const tampered = { ...jws, payload: Buffer.from("{}").toString("base64url") };
+	const err = t.throws(() => verifyFlattenedJws(tampered, hsClient));
+	t.is(err?.code, ErrorCode.InvalidSignature);
+});
+
+test("should reject a parameter in both protected and unprotected headers", (t) => {
+	const err = t.throws(() => signFlattenedJws(payload, { client: hsClient, header: { keyId: "other" } }));
+	t.is(err?.code, ErrorCode.Malformed);
+});
+
+test("should reject critical parameters in the unprotected header", (t) => {
+	const jws = signFlattenedJws(payload, { client: hsClient });
+
+	const err = t.throws(() => verifyFlattenedJws({ ...jws, header: { crit: ["exp"] } }, hsClient));
+	t.is(err?.code, ErrorCode.Malformed);
+});
+
+test("should not verify an HMAC keyed with an RSA public key", (t) => {
+	const { rsClient, forgeSignature } = forgedHmacFixture();
+	const encodedPayload = Buffer.from(payload).toString("base64url");
+	const signature = forgeSignature(`${hs256Header}.${encodedPayload}`);
+
+	const err = t.throws(() =>
+		verifyFlattenedJws({ payload: encodedPayload, protected: hs256Header, signature }, rsClient),
+	);
+	t.is(err?.code, ErrorCode.InvalidAlgorithm);
+});
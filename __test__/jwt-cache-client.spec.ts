@@ -0,0 +1,119 @@
+import { ErrorCode, JwtCacheClient } from "../index";
+import test from "ava";
+
+const secret = "testsecretkeycanbeexposed";
+const testPayload = { user: "test@carbonteq.dev" };
+
+const nowSecs = () => Math.floor(Date.now() / 1000);
+
+test("revoked token should fail even if cached", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const token = client.sign(testPayload);
+	t.deepEqual(client.verify(token).data, testPayload);
+
+	client.revoke(token);
+	const err = t.throws<Error & { code: string }>(() => client.verify(token));
+	t.is(err?.code, ErrorCode.Revoked);
+});
+
+test("revoking a jti should only affect tokens with that jti", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const revoked = client.sign(testPayload, { jti: "a" });
+	const kept = client.sign(testPayload, { jti: "b" });
+
+	client.revokeJti("a");
+	const err = t.throws<Error & { code: string; claim?: string }>(() => client.verify(revoked));
+	t.is(err?.code, ErrorCode.Revoked);
+	t.is(err?.claim, "jti");
+	t.is(client.verify(kept).jti, "b");
+});
+
+test("revocations should be kept against the client's clock", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100, { clock: { now: 1_700_000_000 } });
+	const revoked = client.sign(testPayload, { jti: "a" });
+
+	client.revokeJti("a");
+	client.revokeJti("b");
+	t.throws(() => client.verify(revoked), { code: ErrorCode.Revoked });
+});
+
+test("revoking a token should verify it first", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const forged = new JwtCacheClient("anothersecret", 100, 100).sign(testPayload);
+
+	t.throws(() => client.revoke(forged), { code: ErrorCode.InvalidSignature });
+
+	// Only accepted with a per-call validation, but revocable all the same
+	const token = client.sign(testPayload, { aud: "api" });
+	t.is(client.verify(token, { aud: ["api"] }).aud, "api");
+	client.revoke(token);
+	t.throws(() => client.verify(token, { aud: ["api"] }), { code: ErrorCode.Revoked });
+});
+
+test("revoked jtis should be kept until lifted or until keepUntil", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const lifted = client.sign(testPayload, { jti: "a" });
+	const dropped = client.sign(testPayload, { jti: "b" });
+
+	client.revokeJti("a");
+	client.revokeJti("b", nowSecs() - 120);
+	// Revoking prunes the revocations past their `keepUntil`
+	client.revokeJti("c");
+	t.throws(() => client.verify(lifted), { code: ErrorCode.Revoked });
+	t.is(client.verify(dropped).jti, "b");
+
+	client.unrevokeJti("a");
+	t.is(client.verify(lifted).jti, "a");
+});
+
+test("revoking a subject should only affect tokens issued before the cutoff", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const old = client.sign(testPayload, { sub: "user", iat: nowSecs() - 10 });
+	const fresh = client.sign(testPayload, { sub: "user", iat: nowSecs() + 10 });
+
+	client.revokeSubject("user", nowSecs());
+	const [oldRes, freshRes] = client.verifyMany([old, fresh]);
+	t.is(oldRes.error?.code, ErrorCode.Revoked);
+	t.is(oldRes.error?.claim, "sub");
+	t.is(freshRes.claims?.sub, "user");
+});
+
+test("per-call validation should not reuse results cached under another validation", (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const token = client.sign(testPayload, { sub: "user" });
+
+	t.is(client.verify(token).sub, "user");
+	const err = t.throws<Error & { code: string }>(() => client.verify(token, { sub: "admin" }));
+	t.is(err?.code, ErrorCode.InvalidSubject);
+	t.is(client.verify(token, { sub: "user" }).sub, "user");
+});
+
+test("cached tokens should fail once they expire", async (t) => {
+	const client = new JwtCacheClient(secret, 1, 100, { validation: { leeway: 0 } });
+	const token = client.sign(testPayload);
+	const longLived = new JwtCacheClient(secret, 100, 100, { validation: { leeway: 0 } });
+
+	t.deepEqual(longLived.verify(token).data, testPayload);
+	await new Promise((resolve) => setTimeout(resolve, 2100));
+	t.throws(() => longLived.verify(token), { code: ErrorCode.Expired });
+});
+
+test("cached tokens signed with a rotated key should fail after the grace period", async (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const token = client.sign(testPayload);
+
+	t.deepEqual(client.verify(token).data, testPayload);
+	client.rotateKeys("anothersecretkeycanbeexposed", { gracePeriodSecs: 0 });
+	await new Promise((resolve) => setTimeout(resolve, 1100));
+	t.throws(() => client.verify(token), { code: ErrorCode.InvalidSignature });
+});
+
+test("tokens cached after a rotation should fail once the grace period is over", async (t) => {
+	const client = new JwtCacheClient(secret, 100, 100);
+	const token = client.sign(testPayload);
+
+	client.rotateKeys("anothersecretkeycanbeexposed", { gracePeriodSecs: 1 });
+	t.deepEqual(client.verify(token).data, testPayload);
+	await new Promise((resolve) => setTimeout(resolve, 2100));
+	t.throws(() => client.verify(token), { code: ErrorCode.InvalidSignature });
+});
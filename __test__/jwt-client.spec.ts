@@ -1,6 +1,7 @@
-import { Claims, JwtClient } from "../index";
+import { Claims, ErrorCode, InMemoryReplayGuard, JtiFormat, JwtClient } from "../index";
 import test from "ava";
 import * as jose from "jose";
+import { forgedHmacFixture, hs256Header } from "./forged-hmac";
 
 const secret = "testsecretkeycanbeexposed";
 const normalExpiresIn = 10000;
@@ -27,7 +28,442 @@ test("verifying after exp should throw error", (t) => {
 	claims.exp = 10;
 
 	const token = client.signClaims(claims);
-	t.throws(() => {
-		client.verify(token);
+	t.throws(
+		() => {
+			client.verify(token);
+		},
+		{ code: ErrorCode.Expired },
+	);
+});
+
+test("fractional exp should be rounded rather than treated as expired", (t) => {
+	const claims = new Claims(testPayload, 1);
+	claims.exp = Math.floor(Date.now() / 1000) + normalExpiresIn + 0.5;
+
+	const token = client.signClaims(claims);
+	t.deepEqual(client.verify(token).data, testPayload);
+});
+
+test("jwk header should round trip through the client header", (t) => {
+	const jwk = { keyType: "oct", keyId: "embedded", value: "c2VjcmV0" };
+	const jwkClient = new JwtClient(secret, { header: { jwk } });
+
+	t.like(jwkClient.header.jwk, jwk);
+});
+
+test("verifyComplete should return header with claims", (t) => {
+	const kidClient = new JwtClient(secret, { header: { keyId: "test-kid" } });
+	const token = kidClient.sign(testPayload, normalExpiresIn);
+	const { header, claims } = kidClient.verifyComplete(token);
+
+	t.is(header.keyId, "test-kid");
+	t.deepEqual(claims.data, testPayload);
+});
+
+test("signAsync and verifyAsync should round trip", async (t) => {
+	const token = await client.signAsync(testPayload, normalExpiresIn);
+	const claims = await client.verifyAsync(token);
+
+	t.deepEqual(claims.data, testPayload);
+});
+
+test("verifyAsync should reject invalid tokens", async (t) => {
+	const claims = new Claims(testPayload, 1);
+	claims.exp = 10;
+
+	const token = client.signClaims(claims);
+	await t.throwsAsync(client.verifyAsync(token));
+});
+
+test("verifyMany should report failures per token", async (t) => {
+	const valid = client.sign(testPayload, normalExpiresIn);
+	const tokens = [valid, "not.a.token", valid];
+
+	for (const results of [client.verifyMany(tokens), await client.verifyManyAsync(tokens)]) {
+		t.is(results.length, 3);
+		t.deepEqual(results[0].claims?.data, testPayload);
+		t.truthy(results[1].error?.message);
+		t.is(results[1].claims, undefined);
+		t.deepEqual(results[2].claims?.data, testPayload);
+	}
+});
+
+test("signMany should produce verifiable tokens in order", (t) => {
+	const tokens = client.signMany([
+		{ data: testPayload, expiresInSeconds: normalExpiresIn },
+		{ data: { user: "other" }, expiresInSeconds: normalExpiresIn, claimOpts: { sub: "other" } },
+	]);
+
+	t.is(tokens.length, 2);
+	t.deepEqual(client.verify(tokens[0]).data, testPayload);
+	t.is(client.verify(tokens[1]).sub, "other");
+});
+
+test("missing required claim should be reported with its name", (t) => {
+	const strictClient = new JwtClient(secret, {
+		validation: { requiredSpecClaims: ["exp", "sub"] },
+	});
+	const token = strictClient.sign(testPayload, normalExpiresIn);
+
+	const err = t.throws<Error & { code: string; claim?: string }>(() => strictClient.verify(token));
+	t.is(err?.code, ErrorCode.MissingClaim);
+	t.is(err?.claim, "sub");
+});
+
+test("array aud should be signed and verified", async (t) => {
+	const audClient = new JwtClient(secret, { validation: { aud: ["web"] } });
+	const token = audClient.sign(testPayload, normalExpiresIn, { aud: ["api", "web"] });
+	const joseVerifyRes = await jose.jwtVerify(token, secretEnc, { audience: "web" });
+
+	t.deepEqual(joseVerifyRes.payload.aud, ["api", "web"]);
+	t.deepEqual(audClient.verify(token).aud, ["api", "web"]);
+});
+
+test("flat claims should sit beside registered claims", async (t) => {
+	const flatClient = new JwtClient(secret, { flatClaims: true });
+	const token = flatClient.sign(testPayload, normalExpiresIn, { iss: "carbonteq" });
+	const joseVerifyRes = await jose.jwtVerify(token, secretEnc);
+
+	t.is(joseVerifyRes.payload.user, testPayload.user);
+	t.is(joseVerifyRes.payload.data, undefined);
+
+	const claims = flatClient.verify(token);
+	t.is(claims.user, testPayload.user);
+	t.is(claims.iss, "carbonteq");
+	t.is(claims.data, undefined);
+});
+
+test("flat claims should verify third-party tokens", async (t) => {
+	const flatClient = new JwtClient(secret, { flatClaims: true });
+	const token = await new jose.SignJWT({ role: "admin" })
+		.setProtectedHeader({ alg: "HS256" })
+		.setExpirationTime("1h")
+		.sign(secretEnc);
+
+	const claims = flatClient.verify(token);
+	t.is(claims.role, "admin");
+	t.is(claims.data, undefined);
+});
+
+test("flat claims should reject custom claims named like registered ones", (t) => {
+	const flatClient = new JwtClient(secret, { flatClaims: true });
+
+	const err = t.throws<Error & { code: string }>(() =>
+		flatClient.sign({ exp: 1 }, normalExpiresIn),
+	);
+	t.is(err?.code, ErrorCode.ReservedClaim);
+});
+
+test("default claims should be merged with per-call claim opts", (t) => {
+	const defaultsClient = new JwtClient(secret, {
+		validation: { aud: ["api"] },
+		defaultClaims: { iss: "carbonteq", aud: "api", setIat: true, generateJti: JtiFormat.UuidV4 },
+	});
+
+	const claims = defaultsClient.verify(defaultsClient.sign(testPayload, normalExpiresIn));
+	t.is(claims.iss, "carbonteq");
+	t.is(claims.aud, "api");
+	t.is(typeof claims.iat, "number");
+	t.regex(claims.jti ?? "", /^[0-9a-f-]{36}$/);
+
+	const overridden = defaultsClient.verify(
+		defaultsClient.sign(testPayload, normalExpiresIn, { iss: "other" }),
+	);
+	t.is(overridden.iss, "other");
+});
+
+test("claims should get a generated jti in the requested format", (t) => {
+	t.regex(new Claims(testPayload, 1, { generateJti: JtiFormat.Ulid }).jti ?? "", /^[0-9A-HJKMNP-TV-Z]{26}$/);
+	t.is(new Claims(testPayload, 1, { jti: "given", generateJti: JtiFormat.UuidV7 }).jti, "given");
+});
+
+test("replay guard should reject a token verified before", (t) => {
+	const oneTimeClient = new JwtClient(secret, {
+		replayGuard: new InMemoryReplayGuard(100),
+		defaultClaims: { generateJti: JtiFormat.UuidV7 },
+	});
+	const token = oneTimeClient.sign(testPayload, normalExpiresIn);
+
+	t.deepEqual(oneTimeClient.verify(token).data, testPayload);
+	const err = t.throws<Error & { code: string; claim?: string }>(() => oneTimeClient.verify(token));
+	t.is(err?.code, ErrorCode.Replayed);
+	t.is(err?.claim, "jti");
+});
+
+test("replay guard should apply to verifyPayload", (t) => {
+	const oneTimeClient = new JwtClient(secret, {
+		replayGuard: new InMemoryReplayGuard(100),
+		defaultClaims: { generateJti: JtiFormat.UuidV7 },
+	});
+	const token = oneTimeClient.sign(testPayload, normalExpiresIn);
+
+	t.deepEqual(oneTimeClient.verifyPayload(token).data, testPayload);
+	t.throws(() => oneTimeClient.verifyPayload(token), { code: ErrorCode.Replayed });
+	t.throws(() => oneTimeClient.verifyPayload(oneTimeClient.signPayload(Buffer.from("bytes"))), {
+		code: ErrorCode.MissingClaim,
+	});
+});
+
+test("in-memory replay guard should reject new ids while full", (t) => {
+	const oneTimeClient = new JwtClient(secret, { replayGuard: new InMemoryReplayGuard(1) });
+
+	oneTimeClient.verify(oneTimeClient.sign(testPayload, normalExpiresIn, { jti: "first" }));
+	t.throws(() => oneTimeClient.verify(oneTimeClient.sign(testPayload, normalExpiresIn, { jti: "second" })), {
+		code: ErrorCode.Generic,
+	});
+});
+
+test("replay guard may be a function keeping the ids elsewhere", async (t) => {
+	const seen = new Map<string, number>();
+	const oneTimeClient = new JwtClient(secret, {
+		replayGuard: (jti, expiresAt) => !seen.has(jti) && Boolean(seen.set(jti, expiresAt)),
+	});
+	const token = oneTimeClient.sign(testPayload, normalExpiresIn, { jti: "once" });
+
+	const claims = await oneTimeClient.verifyAsync(token);
+	t.is(seen.get("once"), claims.exp + 60);
+	t.throws(() => oneTimeClient.verify(token), { code: ErrorCode.Replayed });
+});
+
+test("rotated client should sign with the new key and accept the old one", (t) => {
+	const rotatingClient = new JwtClient(secret, { header: { keyId: "k1" } });
+	const oldToken = rotatingClient.sign(testPayload, normalExpiresIn);
+
+	rotatingClient.rotateKeys("anothersecretkey", { gracePeriodSecs: 60, keyId: "k2" });
+	const newToken = rotatingClient.sign(testPayload, normalExpiresIn);
+
+	t.is(rotatingClient.header.keyId, "k2");
+	t.deepEqual(rotatingClient.verify(oldToken).data, testPayload);
+	t.deepEqual(rotatingClient.verify(newToken).data, testPayload);
+	t.throws(() => client.verify(newToken));
+});
+
+test("per-call validation should be layered over the client validation", async (t) => {
+	const token = client.sign(testPayload, normalExpiresIn, { iss: "carbonteq" });
+
+	t.is(client.verify(token, { iss: ["carbonteq"] }).iss, "carbonteq");
+	const err = t.throws<Error & { code: string }>(() => client.verify(token, { iss: ["other"] }));
+	t.is(err?.code, ErrorCode.InvalidIssuer);
+	await t.throwsAsync(client.verifyAsync(token, { iss: ["other"] }));
+});
+
+test("claim validators should name the offending claim", (t) => {
+	const strictClient = new JwtClient(secret, {
+		validation: {
+			requiredClaims: ["tenant"],
+			claimEquals: { tenant: "carbonteq" },
+			claimOneOf: { plan: ["pro", "team"] },
+			claimContains: { groups: ["dev"] },
+		},
+	});
+	const validData = { tenant: "carbonteq", plan: "pro", groups: ["dev", "ops"] };
+
+	t.deepEqual(strictClient.verify(strictClient.sign(validData, normalExpiresIn)).data, validData);
+
+	const cases: Array<[Record<string, unknown>, ErrorCode, string]> = [
+		[{ plan: "pro", groups: ["dev"] }, ErrorCode.MissingClaim, "tenant"],
+		[{ ...validData, tenant: "other" }, ErrorCode.InvalidClaim, "tenant"],
+		[{ ...validData, plan: "free" }, ErrorCode.InvalidClaim, "plan"],
+		[{ ...validData, groups: ["ops"] }, ErrorCode.InvalidClaim, "groups"],
+	];
+	for (const [data, code, claim] of cases) {
+		const err = t.throws<Error & { code: string; claim?: string }>(() =>
+			strictClient.verify(strictClient.sign(data, normalExpiresIn)),
+		);
+		t.is(err?.code, code);
+		t.is(err?.claim, claim);
+	}
+});
+
+test("scopes and roles should be read from scope, scp and roles", (t) => {
+	const claims = new Claims({ scope: "read write", scp: ["admin"], roles: ["editor"] }, normalExpiresIn);
+
+	t.deepEqual(claims.scopes(), ["read", "write", "admin"]);
+	t.true(claims.hasScopes(["read", "admin"]));
+	t.false(claims.hasScopes(["read", "delete"]));
+	t.true(claims.hasAnyScope(["delete", "write"]));
+	t.true(claims.hasRoles(["editor"]));
+	t.false(claims.hasAnyRole(["owner"]));
+});
+
+test("scope requirements should fail with insufficient scope", (t) => {
+	const scopedClient = new JwtClient(secret, {
+		validation: { scopes: { allOf: ["read"], anyOf: ["write", "admin"] }, roles: { anyOf: ["editor"] } },
+	});
+
+	const token = scopedClient.sign({ scope: "read write", roles: ["editor"] }, normalExpiresIn);
+	t.true(scopedClient.verify(token).hasScopes(["read", "write"]));
+
+	const cases: Array<[Record<string, unknown>, string]> = [
+		[{ scope: "write", roles: ["editor"] }, "scope"],
+		[{ scp: ["read"], roles: ["editor"] }, "scp"],
+		[{ scope: "read admin", roles: ["viewer"] }, "roles"],
+	];
+	for (const [data, claim] of cases) {
+		const err = t.throws<Error & { code: string; claim?: string }>(() =>
+			scopedClient.verify(scopedClient.sign(data, normalExpiresIn)),
+		);
+		t.is(err?.code, ErrorCode.InsufficientScope);
+		t.is(err?.claim, claim);
+	}
+});
+
+test("maxAge and validateIat should bound the token's age", (t) => {
+	const ageClient = new JwtClient(secret, { validation: { maxAge: 3600, validateIat: true, leeway: 0 } });
+	const now = Math.floor(Date.now() / 1000);
+	const verifyErr = (iat?: number) =>
+		t.throws<Error & { code: string; claim?: string }>(() =>
+			ageClient.verify(ageClient.sign(testPayload, normalExpiresIn, { iat })),
+		);
+
+	t.is(ageClient.verify(ageClient.sign(testPayload, normalExpiresIn, { iat: now - 60 })).iat, now - 60);
+
+	const tooOld = verifyErr(now - 7200);
+	t.is(tooOld?.code, ErrorCode.Expired);
+	t.is(tooOld?.claim, "iat");
+
+	const inFuture = verifyErr(now + 600);
+	t.is(inFuture?.code, ErrorCode.NotYetValid);
+	t.is(inFuture?.claim, "iat");
+
+	t.is(verifyErr()?.code, ErrorCode.MissingClaim);
+});
+
+test("requireIat should reject tokens without iat", (t) => {
+	const token = client.sign(testPayload, normalExpiresIn);
+
+	const err = t.throws<Error & { code: string; claim?: string }>(() => client.verify(token, { requireIat: true }));
+	t.is(err?.code, ErrorCode.MissingClaim);
+	t.is(err?.claim, "iat");
+});
+
+test("clock should be used for signing and for checking exp and nbf", (t) => {
+	const now = 1_700_000_000;
+	const pastClient = new JwtClient(secret, {
+		clock: { now },
+		defaultClaims: { setIat: true, nbfOffset: 60 },
+		validation: { validateNbf: true, leeway: 0 },
 	});
+
+	const token = pastClient.sign(testPayload, 1000);
+	const claims = jose.decodeJwt(token);
+	t.is(claims.exp, now + 1000);
+	t.is(claims.iat, now);
+
+	const notYetValid = t.throws<Error & { code: string }>(() => pastClient.verify(token));
+	t.is(notYetValid?.code, ErrorCode.NotYetValid);
+
+	const laterClient = new JwtClient(secret, { clock: { now, offset: 120 }, validation: { validateNbf: true, leeway: 0 } });
+	t.deepEqual(laterClient.verify(token).data, testPayload);
+
+	const expired = t.throws<Error & { code: string }>(() => client.verify(token));
+	t.is(expired?.code, ErrorCode.Expired);
+});
+
+test("clock should be used for checking a fractional nbf", (t) => {
+	const now = 1_700_000_000;
+	const pastClient = new JwtClient(secret, { clock: { now }, validation: { validateNbf: true, leeway: 0 } });
+	const claims = new Claims(testPayload, normalExpiresIn);
+	claims.nbf = now + 60.5;
+
+	const token = pastClient.signClaims(claims);
+	t.throws(() => pastClient.verify(token), { code: ErrorCode.NotYetValid });
+});
+
+test("clock should be used to remember seen jtis", (t) => {
+	const oneTimeClient = new JwtClient(secret, {
+		clock: { now: 1_700_000_000 },
+		replayGuard: new InMemoryReplayGuard(100),
+	});
+	const token = oneTimeClient.sign(testPayload, normalExpiresIn, { jti: "past" });
+
+	oneTimeClient.verify(token);
+	t.throws(() => oneTimeClient.verify(token), { code: ErrorCode.Replayed });
+});
+
+test("detached payloads should be signed unencoded", async (t) => {
+	const payload = Buffer.from('{"event":"invoice.paid","amount":100}');
+	const jws = client.signDetached(payload);
+	const [encodedHeader, encodedPayload] = jws.split(".");
+
+	t.is(encodedPayload, "");
+	t.deepEqual(JSON.parse(Buffer.from(encodedHeader, "base64url").toString()), {
+		alg: "HS256",
+		b64: false,
+		crit: ["b64"],
+	});
+	t.is(client.verifyDetached(jws, payload).algorithm, "HS256");
+
+	const joseRes = await jose.flattenedVerify(
+		{ protected: encodedHeader, payload, signature: jws.split(".")[2] },
+		secretEnc,
+	);
+	t.deepEqual(Buffer.from(joseRes.payload), payload);
+});
+
+test("detached payloads should fail to verify when changed", (t) => {
+	const jws = client.signDetached(Buffer.from("original"));
+
+	const err = t.throws<Error & { code: string }>(() => client.verifyDetached(jws, Buffer.from("changed")));
+	t.is(err?.code, ErrorCode.InvalidSignature);
+	t.throws(() => client.verifyDetached(client.sign(testPayload, normalExpiresIn), Buffer.from("original")), {
+		code: ErrorCode.Malformed,
+	});
+});
+
+test("detached payloads should not verify with a key of another family", (t) => {
+	const { rsClient, forgeSignature } = forgedHmacFixture();
+	const payload = Buffer.from("forged");
+	const signature = forgeSignature(`${hs256Header}.${payload.toString("base64url")}`);
+
+	t.throws(() => rsClient.verifyDetached(`${hs256Header}..${signature}`, payload), {
+		code: ErrorCode.InvalidAlgorithm,
+	});
+});
+
+test("signPayload should sign any value without adding claims", async (t) => {
+	const payload = { event: "invoice.paid", amount: 100 };
+	const token = client.signPayload(payload);
+
+	const joseRes = await jose.compactVerify(token, secretEnc);
+	t.deepEqual(JSON.parse(Buffer.from(joseRes.payload).toString()), payload);
+	t.deepEqual(client.verifyPayload(token), payload);
+	t.deepEqual(client.verifyPayload(client.signPayload([1, "two"])), [1, "two"]);
+
+	const bytes = Buffer.from([0xff, 0x00, 0x01]);
+	t.deepEqual(client.verifyPayload(client.signPayload(bytes)), bytes);
+});
+
+test("signPayload should keep bytes that happen to be JSON as bytes", (t) => {
+	const bytes = Buffer.from("123");
+	const token = client.signPayload(bytes);
+
+	t.is(JSON.parse(Buffer.from(token.split(".")[0], "base64url").toString()).cty, "application/octet-stream");
+	t.deepEqual(client.verifyPayload(token), bytes);
+	t.is(client.verifyPayload(client.signPayload(123)), 123);
+});
+
+test("verifyPayload should validate registered claims only if present", (t) => {
+	t.deepEqual(client.verifyPayload(client.signPayload({ id: 1 }), { iss: ["issuer"] }), { id: 1 });
+
+	t.throws(() => client.verifyPayload(client.signPayload({ exp: 1000 })), { code: ErrorCode.Expired });
+	t.throws(() => client.verifyPayload(client.signPayload({ iss: "other" }), { iss: ["issuer"] }), {
+		code: ErrorCode.InvalidIssuer,
+	});
+});
+
+test("verifyPayload should leave registered claim names with other types unvalidated", (t) => {
+	t.deepEqual(client.verifyPayload(client.signPayload({ sub: 42 })), { sub: 42 });
+	t.deepEqual(client.verifyPayload(client.signPayload({ iat: "x", exp: "soon" })), { iat: "x", exp: "soon" });
+	t.throws(() => client.verifyPayload(client.signPayload({ iss: 1 }), { iss: ["issuer"] }), {
+		code: ErrorCode.InvalidIssuer,
+	});
+});
+
+test("verifyPayload should not verify an HMAC keyed with an RSA public key", (t) => {
+	const { rsClient, forgeSignature } = forgedHmacFixture();
+	const message = `${hs256Header}.${Buffer.from(JSON.stringify({ sub: "admin" })).toString("base64url")}`;
+	const signature = forgeSignature(message);
+
+	t.throws(() => rsClient.verifyPayload(`${message}.${signature}`), { code: ErrorCode.InvalidAlgorithm });
 });
@@ -0,0 +1,46 @@
+import { generateKeyPairSync } from "node:crypto";
+import { Algorithm, ErrorCode, JwtClient, JwtKeyring } from "../index";
+import test from "ava";
+
+const { publicKey, privateKey } = generateKeyPairSync("rsa", {
+	modulusLength: 2048,
+});
+const pubPem = publicKey.export({ type: "spki", format: "pem" });
+const privPem = privateKey.export({ type: "pkcs8", format: "pem" });
+
+const testPayload = { user: "test@carbonteq.dev" };
+
+const keyring = new JwtKeyring();
+keyring.addKey("hs-1", Algorithm.HS256, "testsecretkeycanbeexposed", "internal");
+keyring.addKey("rs-1", Algorithm.RS256, pubPem, "partner");
+keyring.setIssuerValidation("partner", { aud: ["api"] });
+
+test("should select the key by kid", (t) => {
+	const signer = JwtClient.withPubPrivKeys(pubPem, privPem, {
+		header: { algorithm: Algorithm.RS256, keyId: "rs-1" },
+	});
+	const token = signer.sign(testPayload, 10000, { iss: "partner", aud: "api" });
+
+	t.deepEqual(keyring.verify(token).data, testPayload);
+});
+
+test("should select the key by iss when there is no kid", (t) => {
+	const signer = new JwtClient("testsecretkeycanbeexposed");
+
+	t.deepEqual(keyring.verify(signer.sign(testPayload, 10000, { iss: "internal" })).data, testPayload);
+
+	const err = t.throws<Error & { code: string }>(() =>
+		keyring.verify(signer.sign(testPayload, 10000, { iss: "unknown" })),
+	);
+	t.is(err?.code, ErrorCode.NoMatchingKey);
+});
+
+test("should apply the validation of the key's issuer", (t) => {
+	const signer = JwtClient.withPubPrivKeys(pubPem, privPem, {
+		header: { algorithm: Algorithm.RS256, keyId: "rs-1" },
+	});
+	const token = signer.sign(testPayload, 10000, { iss: "partner", aud: "web" });
+
+	const err = t.throws<Error & { code: string }>(() => keyring.verify(token));
+	t.is(err?.code, ErrorCode.InvalidAudience);
+});
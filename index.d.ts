@@ -30,8 +30,8 @@ export const enum Algorithm {
   EdDSA = 'EdDSA'
 }
 export interface ClaimOpts {
-  /** Recipient for which the JWT is intended */
-  aud?: string
+  /** Recipient(s) for which the JWT is intended */
+  aud?: string | Array<string>
   /** Time at which the JWT was issued (as UTC timestamp, seconds from epoch time) */
   iat?: number
   /** Issuer of JWT */
@@ -42,6 +42,169 @@ export interface ClaimOpts {
   nbf?: number
   /** Subject of JWT (the user) */
   sub?: string
+  /** If set and `jti` is not given, a unique `jti` in this format is generated */
+  generateJti?: JtiFormat
+}
+/** How generated `jti` claims are formatted */
+export const enum JtiFormat {
+  /** Random UUID, e.g. `9b2f6b8e-3c1a-4d5e-8f7a-0b1c2d3e4f5a` */
+  UuidV4 = 'uuid-v4',
+  /** Time-ordered UUID, e.g. `019a1f4e-7b2c-7d3e-9f4a-5b6c7d8e9f0a` */
+  UuidV7 = 'uuid-v7',
+  /** Time-ordered ULID, e.g. `01JAB3M8Q4W5E6R7T8Y9V0X1Z2` */
+  Ulid = 'ulid'
+}
+/** Claims set on every token signed by a client, unless given in the per-call `ClaimOpts` */
+export interface DefaultClaims {
+  /** Recipient(s) for which the JWT is intended */
+  aud?: string | Array<string>
+  /** Issuer of JWT */
+  iss?: string
+  /**
+   * Whether to set `iat` to the time of signing.
+   *
+   * Defaults to `false`.
+   */
+  setIat?: boolean
+  /** If set, `nbf` is the time of signing plus this many seconds (negative values backdate it) */
+  nbfOffset?: number
+  /** If set, a unique `jti` in this format is generated for each token */
+  generateJti?: JtiFormat
+}
+/** Time used instead of the system time when signing and verifying, e.g. to test expiry */
+export interface Clock {
+  /** Fixed current time (as UTC timestamp, seconds from epoch time) */
+  now?: number
+  /** Seconds added to the current time, or to `now` if set */
+  offset?: number
+}
+export interface UnverifiedToken {
+  header: Header
+  payload: Record<string, any>
+}
+/** Decode the header of a token without verifying its signature */
+export function decodeHeader(token: string): Header
+/**
+ * Decode the header and payload of a token WITHOUT verifying its signature or claims.
+ *
+ * Only use this to inspect a token (e.g. to pick the client that should verify it), never to
+ * trust its contents.
+ */
+export function decodeUnverified(token: string): UnverifiedToken
+/** Stable codes set as the `code` property of every error thrown by this package */
+export const enum ErrorCode {
+  /** The key could not be parsed for the selected algorithm */
+  InvalidKey = 'ERR_JWT_INVALID_KEY',
+  /** The token is not a well formed JWT, or its payload does not have the expected shape */
+  Malformed = 'ERR_JWT_MALFORMED',
+  /** The signature does not match the token's header and payload */
+  InvalidSignature = 'ERR_JWT_INVALID_SIGNATURE',
+  /** The token's algorithm is not allowed by the validation, or does not fit the key */
+  InvalidAlgorithm = 'ERR_JWT_INVALID_ALGORITHM',
+  /** The `exp` claim is in the past, or the token is older than the validation's `maxAge` */
+  Expired = 'ERR_JWT_EXPIRED',
+  /** The `nbf` claim, or the `iat` claim if it is validated, is in the future */
+  NotYetValid = 'ERR_JWT_NOT_YET_VALID',
+  /** The `iss` claim does not match the validation */
+  InvalidIssuer = 'ERR_JWT_INVALID_ISSUER',
+  /** The `aud` claim does not match the validation */
+  InvalidAudience = 'ERR_JWT_INVALID_AUDIENCE',
+  /** The `sub` claim does not match the validation */
+  InvalidSubject = 'ERR_JWT_INVALID_SUBJECT',
+  /** A claim required by the validation is missing */
+  MissingClaim = 'ERR_JWT_MISSING_CLAIM',
+  /** A claim does not have a value allowed by the validation */
+  InvalidClaim = 'ERR_JWT_INVALID_CLAIM',
+  /** The `scope`, `scp` or `roles` claim does not grant what the validation requires */
+  InsufficientScope = 'ERR_JWT_INSUFFICIENT_SCOPE',
+  /** The token's `jti` was already accepted once by the replay guard */
+  Replayed = 'ERR_JWT_REPLAYED',
+  /** The token, its `jti` or its `sub` was revoked */
+  Revoked = 'ERR_JWT_REVOKED',
+  /** A custom claim uses the name of a registered claim */
+  ReservedClaim = 'ERR_JWT_RESERVED_CLAIM',
+  /** The encrypted token could not be decrypted with the key, or was tampered with */
+  DecryptionFailed = 'ERR_JWE_DECRYPTION_FAILED',
+  /** No key in the set matches the token's `kid` */
+  NoMatchingKey = 'ERR_JWKS_NO_MATCHING_KEY',
+  /** Anything else, e.g. a failure inside the crypto backend */
+  Generic = 'ERR_JWT_GENERIC'
+}
+/** Error details for results that are returned instead of thrown */
+export interface ErrorInfo {
+  /** Same as the `code` of the equivalent thrown error */
+  code: ErrorCode
+  message: string
+  /** Name of the offending claim, if the failure is about a single claim */
+  claim?: string
+}
+export interface Jwk {
+  /**
+   * Key type: `RSA`, `EC`, `OKP` or `oct`
+   *
+   * Defined in [RFC7517#4.1](https://tools.ietf.org/html/rfc7517#section-4.1).
+   */
+  keyType: string
+  /**
+   * Intended use of the public key: `sig` or `enc`
+   *
+   * Defined in [RFC7517#4.2](https://tools.ietf.org/html/rfc7517#section-4.2).
+   */
+  publicKeyUse?: string
+  /**
+   * Operations the key is intended to be used for
+   *
+   * Defined in [RFC7517#4.3](https://tools.ietf.org/html/rfc7517#section-4.3).
+   */
+  keyOperations?: Array<string>
+  /**
+   * Algorithm intended for use with the key
+   *
+   * Defined in [RFC7517#4.4](https://tools.ietf.org/html/rfc7517#section-4.4).
+   */
+  keyAlgorithm?: string
+  /**
+   * Key ID
+   *
+   * Defined in [RFC7517#4.5](https://tools.ietf.org/html/rfc7517#section-4.5).
+   */
+  keyId?: string
+  /**
+   * X.509 URL
+   *
+   * Defined in [RFC7517#4.6](https://tools.ietf.org/html/rfc7517#section-4.6).
+   */
+  x5Url?: string
+  /**
+   * X.509 certificate chain. A Vec of base64 encoded ASN.1 DER certificates.
+   *
+   * Defined in [RFC7517#4.7](https://tools.ietf.org/html/rfc7517#section-4.7).
+   */
+  x5CertChain?: Array<string>
+  /**
+   * X.509 SHA1 certificate thumbprint
+   *
+   * Defined in [RFC7517#4.8](https://tools.ietf.org/html/rfc7517#section-4.8).
+   */
+  x5CertThumbprint?: string
+  /**
+   * X.509 SHA256 certificate thumbprint
+   *
+   * Defined in [RFC7517#4.9](https://tools.ietf.org/html/rfc7517#section-4.9).
+   */
+  x5TS256CertThumbprint?: string
+  /** Curve of an `EC` or `OKP` key (`P-256`, `P-384`, `Ed25519`, ...) */
+  curve?: string
+  /** Modulus of an `RSA` key (base64url encoded) */
+  n?: string
+  /** Exponent of an `RSA` key (base64url encoded) */
+  e?: string
+  /** X coordinate of an `EC` key, or public key of an `OKP` key (base64url encoded) */
+  x?: string
+  /** Y coordinate of an `EC` key (base64url encoded) */
+  y?: string
+  /** Key value of an `oct` key (base64url encoded) */
+  value?: string
 }
 export interface Header {
   /**
@@ -55,6 +218,7 @@ export interface Header {
    * Content type
    *
    * Defined in [RFC7519#5.2](https://tools.ietf.org/html/rfc7519#section-5.2).
+   * For nested JWTs it stays on the signed token, as the encrypted one always has `JWT`.
    */
   contentType?: string
   /**
@@ -67,6 +231,9 @@ export interface Header {
    * JSON Web Key
    *
    * Defined in [RFC7515#4.1.3](https://tools.ietf.org/html/rfc7515#section-4.1.3).
+   */
+  jwk?: Jwk
+  /**
    * Key ID
    *
    * Defined in [RFC7515#4.1.4](https://tools.ietf.org/html/rfc7515#section-4.1.4).
@@ -99,9 +266,150 @@ export interface Header {
    */
   x5TS256CertThumbprint?: string
 }
+/** How the content encryption key of a JWE is agreed upon */
+export const enum KeyManagement {
+  /** The 256 bit shared key is used as the content encryption key */
+  Dir = 'dir',
+  /** A random content encryption key, wrapped with the 256 bit shared key using AES Key Wrap */
+  A256KW = 'A256KW',
+  /** A random content encryption key, encrypted with the RSA public key using RSAES-OAEP and SHA-256 */
+  RsaOaep256 = 'RSA-OAEP-256',
+  /** Content encryption key derived by ECDH between an ephemeral key and the P-256 public key */
+  EcdhEs = 'ECDH-ES'
+}
+export interface JweClientInitOpts {
+  /** Defaults to `dir` for a shared key, and to `RSA-OAEP-256` for a key pair */
+  keyManagement?: KeyManagement
+  /** `kid` header of encrypted tokens */
+  keyId?: string
+  validation?: Validation
+  /**
+   * Put custom claims beside `exp`, `iss` etc. instead of under a `data` claim. Decrypted claims
+   * are then returned the same way, as a plain object rather than `Claims`.
+   *
+   * Defaults to `false`.
+   */
+  flatClaims?: boolean
+  /**
+   * Time used for `exp` when encrypting, and for checking `exp`, `nbf` and `iat` when
+   * decrypting. Defaults to the system time.
+   */
+  clock?: Clock
+}
+/** Key of the set that can't be used for verification, and why */
+export interface SkippedJwk {
+  kid?: string
+  reason: string
+}
+/** One signature of a JWS JSON serialization */
+export interface JwsSigner {
+  client: JwtClient
+  /** Set over the client's own header, and covered by the signature */
+  protected?: Header
+  /** Sent alongside the signature, but not covered by it */
+  header?: Header
+}
+export interface JwsSignature {
+  protected: string
+  header?: Record<string, any>
+  signature: string
+}
+/** JWS in the general JSON serialization, with any number of signatures */
+export interface GeneralJws {
+  payload: string
+  signatures: Array<JwsSignature>
+}
+/** JWS in the flattened JSON serialization, with a single signature */
+export interface FlattenedJws {
+  payload: string
+  protected: string
+  header?: Record<string, any>
+  signature: string
+}
+export interface VerifiedJws {
+  payload: Buffer
+  /** Protected and unprotected parameters of each verified signature, in the order of the verifiers */
+  headers: Array<Header>
+}
+/** Sign `payload` once per signer, as a JWS in the general JSON serialization */
+export function signGeneralJws(payload: string | Buffer, signers: Array<JwsSigner>): GeneralJws
+/** Sign `payload` as a JWS in the flattened JSON serialization */
+export function signFlattenedJws(payload: string | Buffer, signer: JwsSigner): FlattenedJws
+/**
+ * Verify a JWS in the general JSON serialization. Every verifier has to match one of the
+ * signatures. Only the signatures are checked, the payload is returned as is.
+ */
+export function verifyGeneralJws(jws: GeneralJws, verifiers: Array<JwtClient>): VerifiedJws
+/**
+ * Verify a JWS in the flattened JSON serialization. Only the signature is checked, the payload
+ * is returned as is.
+ */
+export function verifyFlattenedJws(jws: FlattenedJws, verifier: JwtClient): VerifiedJws
 export interface JwtClientInitOpts {
   header?: Header
   validation?: Validation
+  /**
+   * Put custom claims beside `exp`, `iss` etc. instead of under a `data` claim, as most other
+   * issuers do. Verified claims are then returned the same way, as a plain object rather than
+   * `Claims`.
+   *
+   * Tokens still need an `exp` claim to verify, since `Claims` always has one.
+   *
+   * Defaults to `false`.
+   */
+  flatClaims?: boolean
+  /** Claims added to every token signed with `sign`, `signAsync` and `signMany` */
+  defaultClaims?: DefaultClaims
+  /**
+   * Accept each token only once, rejecting later verifications of the same `jti` until it expires.
+   * Tokens without a `jti` are rejected.
+   *
+   * Either an `InMemoryReplayGuard`, or a function `(jti, expiresAt) => boolean` keeping the ids
+   * elsewhere, which returns `false` if it has seen `jti` before and otherwise remembers it until
+   * `expiresAt` (UTC timestamp, seconds from epoch time). Tokens are rejected if it throws.
+   */
+  replayGuard?: InMemoryReplayGuard | ((jti: string, expiresAt: number) => boolean)
+  /**
+   * Time used for `exp` and `iat` when signing, and for checking `exp`, `nbf` and `iat` when
+   * verifying. Replays, revocations and the grace period of rotated keys are tracked against it
+   * as well. Defaults to the system time.
+   */
+  clock?: Clock
+}
+export interface SignItem {
+  data: Record<string, any>
+  expiresInSeconds: number
+  claimOpts?: ClaimOpts
+}
+export interface VerifiedToken {
+  header: Header
+  claims: Claims | Record<string, any>
+}
+export interface VerifyResult {
+  /** Present if the token was verified successfully */
+  claims?: Claims | Record<string, any>
+  /** Present if verification failed */
+  error?: ErrorInfo
+}
+export interface RotateKeysOpts {
+  /** How long tokens signed with the previous keys are still accepted, in seconds */
+  gracePeriodSecs: number
+  /** Public key matching the new signing key. Required unless the algorithm is symmetric. */
+  publicKey?: string | Buffer
+  /** `kid` header for tokens signed with the new key, used to pick the key when verifying */
+  keyId?: string
+}
+export interface JwtKeyringInitOpts {
+  /** Used for keys without an issuer, or whose issuer has no validation of its own */
+  validation?: Validation
+  /**
+   * Expect custom claims beside `exp`, `iss` etc. instead of under a `data` claim, and return
+   * them the same way, as a plain object rather than `Claims`. Tokens still need an `exp` claim
+   * to verify, since `Claims` always has one.
+   *
+   * Defaults to `false`.
+   */
+  flatClaims?: boolean
 }
 export interface Validation {
   /**
@@ -168,13 +476,51 @@ export interface Validation {
    * Defaults to `true`.
    */
   validateSignature?: boolean
+  /** Claims that must be present, registered or custom. Checked after the signature. */
+  requiredClaims?: Array<string>
+  /** Claims that must be equal to the given values */
+  claimEquals?: Record<string, any>
+  /** Claims that must be equal to one of the given values */
+  claimOneOf?: Record<string, Array<any>>
+  /**
+   * Array claims that must contain all of the given values. A single value counts as an array
+   * of one.
+   */
+  claimContains?: Record<string, Array<any>>
+  /** Scopes that must be granted by the `scope` or `scp` claim */
+  scopes?: ScopeRequirement
+  /** Roles that must be granted by the `roles` claim */
+  roles?: ScopeRequirement
+  /**
+   * Maximum age of the token in seconds, measured from its `iat` claim, which it then must have.
+   * Bounds how long a token is accepted regardless of its `exp`.
+   */
+  maxAge?: number
+  /**
+   * Whether to reject tokens with an `iat` claim in the future.
+   *
+   * Defaults to `false`.
+   */
+  validateIat?: boolean
+  /**
+   * Whether the `iat` claim must be present.
+   *
+   * Defaults to `false`.
+   */
+  requireIat?: boolean
+}
+export interface ScopeRequirement {
+  /** Every one of these must be granted */
+  allOf?: Array<string>
+  /** At least one of these must be granted */
+  anyOf?: Array<string>
 }
 export declare class Claims {
   data: Record<string, any>
   /** Time after which the JWT expires (as UTC timestamp, seconds from epoch time) */
   exp: number
-  /** Recipient for which the JWT is intended */
-  aud?: string
+  /** Recipient(s) for which the JWT is intended */
+  aud?: string | Array<string>
   /** Time at which the JWT was issued (as UTC timestamp, seconds from epoch time) */
   iat?: number
   /** Issuer of JWT */
@@ -186,6 +532,49 @@ export declare class Claims {
   /** Subject of JWT (the user) */
   sub?: string
   constructor(data: Record<string, any>, expiresInSeconds: number, opts?: ClaimOpts | undefined | null)
+  /** Scopes granted by the space-delimited `scope` claim and the `scp` claim */
+  scopes(): Array<string>
+  /** Roles granted by the `roles` claim */
+  roles(): Array<string>
+  hasScopes(scopes: Array<string>): boolean
+  hasAnyScope(scopes: Array<string>): boolean
+  hasRoles(roles: Array<string>): boolean
+  hasAnyRole(roles: Array<string>): boolean
+}
+/** Client for encrypted tokens, as compact JWE using `A256GCM` content encryption */
+export declare class JweClient {
+  /** For `dir` and `A256KW`, with a 256 bit shared key */
+  constructor(secretKey: string | Buffer, opts?: JweClientInitOpts | undefined | null)
+  /** For `RSA-OAEP-256` with an RSA key pair, and `ECDH-ES` with a P-256 key pair */
+  static withPubPrivKeys(pubKey: string | Buffer, privKey: string | Buffer, opts?: JweClientInitOpts | undefined | null): JweClient
+  encrypt(data: Record<string, any>, expiresInSeconds: number, claimOpts?: ClaimOpts | undefined | null): string
+  encryptClaims(claims: Claims): string
+  /**
+   * Sign the claims with `signer`, then encrypt the signed token as a nested JWT. The signer's
+   * header, `contentType` included, describes the signed token and is kept on it, while the JWE
+   * header gets a `cty` of `JWT` (RFC 7519 section 5.2) so that the nesting can be told apart.
+   */
+  signAndEncrypt(signer: JwtClient, data: Record<string, any>, expiresInSeconds: number, claimOpts?: ClaimOpts | undefined | null): string
+  /**
+   * Decrypt a nested JWT, then verify the signed token inside with `verifier` and its validation,
+   * with `validation` layered over it for this call only
+   */
+  decryptAndVerify(verifier: JwtClient, token: string, validation?: Validation | undefined | null): Claims | Record<string, any>
+  /** Decrypt a token and validate its claims */
+  decrypt(token: string): Claims | Record<string, any>
+}
+/** Verifier backed by a JWK Set, selecting the key by the token's `kid` header */
+export declare class JwksClient {
+  /**
+   * Keys that can't be used for verification (malformed, without a `kid`, encryption keys,
+   * unsupported key types) are skipped and listed in `skippedKeys`. Keys sharing a `kid` are all
+   * kept and tried in order.
+   */
+  constructor(jwks: string, validation?: Validation | undefined | null)
+  verify(token: string): Claims
+  get keyIds(): Array<string>
+  /** Keys of the set that were skipped, with the reason for each */
+  get skippedKeys(): Array<SkippedJwk>
 }
 export declare class JwtClient {
   /** For symetric key based signatures */
@@ -193,17 +582,129 @@ export declare class JwtClient {
   /** For assymetric key based signatures */
   static withPubPrivKeys(pubKey: string | Buffer, privKey: string | Buffer, opts?: JwtClientInitOpts | undefined | null): JwtClient
   sign(data: Record<string, any>, expiresInSeconds: number, claimOpts?: ClaimOpts | undefined | null): string
+  signAsync(data: Record<string, any>, expiresInSeconds: number, claimOpts?: ClaimOpts | undefined | null): Promise<string>
   signClaims(claims: Claims): string
-  verify(token: string): Claims
+  /** Sign a batch of payloads in one call, returning the tokens in the same order */
+  signMany(items: Array<SignItem>): Array<string>
+  /** `validation` is layered over the client's own validation for this call only */
+  verify(token: string, validation?: Validation | undefined | null): Claims | Record<string, any>
+  /**
+   * Same as `verify`, off the main thread, with `validation` layered over the client's own
+   * validation for this call only
+   */
+  verifyAsync(token: string, validation?: Validation | undefined | null): Promise<Claims | Record<string, any>>
+  /** `validation` is layered over the client's own validation for this call only */
+  verifyComplete(token: string, validation?: Validation | undefined | null): VerifiedToken
+  /** Verify every token, reporting failures per token instead of throwing */
+  verifyMany(tokens: Array<string>): Array<VerifyResult>
+  /** Same as `verifyMany`, with the tokens split across a pool of threads off the main thread */
+  verifyManyAsync(tokens: Array<string>): Promise<Array<VerifyResult>>
+  /**
+   * Sign `payload` as is, without base64url encoding it (RFC 7797). The payload is left out of the
+   * returned JWS (`header..signature`) and has to be sent alongside it.
+   */
+  signDetached(payload: Buffer): string
+  /** Verify a JWS with a detached payload against `payload`, returning its header */
+  verifyDetached(jws: string, payload: Buffer): Header
+  /**
+   * Sign any JSON value, or raw bytes, as a compact JWS. Unlike `sign`, nothing is added to the
+   * payload, so it has no `exp` unless it carries one itself. Raw bytes are marked with a `cty`
+   * header of `application/octet-stream`.
+   */
+  signPayload(payload: Buffer | unknown): string
+  /**
+   * Verify a token from `signPayload`, with `validation` layered over the client's validation for
+   * this call only. Registered claims are validated only if the payload has them with their
+   * registered type, so `exp` is not required. Returns the payload as a Buffer if the token has a
+   * `cty` header of `application/octet-stream`, and as JSON otherwise. With a replay guard, the
+   * payload must be a JSON object with a `jti`, as with `verify`, and is accepted only once.
+   */
+  verifyPayload(token: string, validation?: Validation | undefined | null): any | Buffer
+  /**
+   * Sign with a new key from now on, while still accepting tokens signed with the previous keys
+   * for `gracePeriodSecs`
+   */
+  rotateKeys(newSigningKey: string | Buffer, opts: RotateKeysOpts): void
   get header(): Header
 }
 export declare class JwtCacheClient {
   constructor(secretKey: string | Buffer, ttlSecs: number, maxCapacity: number, opts?: JwtClientInitOpts | undefined | null)
   static withPubPrivKeys(pubKey: string | Buffer, privKey: string | Buffer, ttlSecs: number, maxCapacity: number, opts?: JwtClientInitOpts | undefined | null): JwtCacheClient
   sign(data: Record<string, any>, claimOpts?: ClaimOpts | undefined | null): string
-  verify(token: string): Claims
+  signAsync(data: Record<string, any>, claimOpts?: ClaimOpts | undefined | null): Promise<string>
+  /** `validation` is layered over the client's own validation for this call only */
+  verify(token: string, validation?: Validation | undefined | null): Claims | Record<string, any>
+  /**
+   * Same as `verify`, off the main thread, with `validation` layered over the client's own
+   * validation for this call only. Results are cached apart for each `validation`.
+   */
+  verifyAsync(token: string, validation?: Validation | undefined | null): Promise<Claims | Record<string, any>>
+  /** `validation` is layered over the client's own validation for this call only */
+  verifyComplete(token: string, validation?: Validation | undefined | null): VerifiedToken
+  /** Verify every token, reporting failures per token instead of throwing */
+  verifyMany(tokens: Array<string>): Array<VerifyResult>
+  /** Same as `verifyMany`, with the tokens split across a pool of threads off the main thread */
+  verifyManyAsync(tokens: Array<string>): Promise<Array<VerifyResult>>
   invalidateCache(): void
+  /**
+   * Sign with a new key from now on, while still accepting tokens signed with the previous keys
+   * for `gracePeriodSecs`. The token cache is cleared, so that tokens verified with the previous
+   * keys are checked against them again, and fail once the grace period is over.
+   */
+  rotateKeys(newSigningKey: string | Buffer, opts: RotateKeysOpts): void
+  /**
+   * Reject this exact token from now on, until it expires. Its signature is checked first, so
+   * that arbitrary tokens cannot be stored, but not its other claims, which a per-call validation
+   * may accept. Expired tokens are left alone, they are rejected anyway.
+   */
+  revoke(token: string): void
+  /**
+   * Reject every token with this `jti` from now on, until `keepUntil` (UTC timestamp, seconds
+   * from epoch time) if given, e.g. the latest `exp` of those tokens, or until `unrevokeJti`
+   */
+  revokeJti(jti: string, keepUntil?: number | undefined | null): void
+  /**
+   * Reject every token for this `sub` issued before `issuedBefore` (UTC timestamp, seconds from
+   * epoch time), e.g. after a password change. Tokens without `iat` are rejected as well. Kept
+   * until `keepUntil` if given, or until `unrevokeSubject`.
+   */
+  revokeSubject(sub: string, issuedBefore: number, keepUntil?: number | undefined | null): void
+  /** Accept tokens with this `jti` again */
+  unrevokeJti(jti: string): void
+  /** Accept tokens for this `sub` again, whenever they were issued */
+  unrevokeSubject(sub: string): void
   get header(): Header
   get ttlSecs(): number
   get maxCapacity(): number
 }
+/**
+ * Verifier for tokens from several issuers or keys, selecting the key by the token's `kid` header,
+ * or by its `iss` claim if it has no `kid`
+ */
+export declare class JwtKeyring {
+  constructor(opts?: JwtKeyringInitOpts | undefined | null)
+  /**
+   * Add a verification key, replacing any key with the same `kid`. Tokens without a `kid` are
+   * matched to the key by `issuer`.
+   */
+  addKey(kid: string, algorithm: Algorithm, key: string | Buffer, issuer?: string | undefined | null): void
+  removeKey(kid: string): void
+  /**
+   * Validation for the keys of `issuer`, used instead of the keyring's default validation.
+   * Unless it sets `iss` itself, the token's `iss` must match `issuer`.
+   */
+  setIssuerValidation(issuer: string, validation: Validation): void
+  verify(token: string): Claims | Record<string, any>
+  get keyIds(): Array<string>
+}
+/**
+ * Replay guard keeping seen `jti`s in memory until their tokens expire, for use with a single
+ * process
+ */
+export declare class InMemoryReplayGuard {
+  /**
+   * Remembers up to `maxCapacity` ids at a time. Ids are forgotten once their tokens expire, and
+   * while `maxCapacity` ids are still remembered, tokens with new ids are rejected.
+   */
+  constructor(maxCapacity: number)
+}
@@ -310,9 +310,22 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { Algorithm, Claims, JwtClient, JwtCacheClient } = nativeBinding
+const { Algorithm, Claims, decodeHeader, decodeUnverified, ErrorCode, InMemoryReplayGuard, JtiFormat, JweClient, JwksClient, JwtClient, JwtCacheClient, JwtKeyring, KeyManagement, signFlattenedJws, signGeneralJws, verifyFlattenedJws, verifyGeneralJws } = nativeBinding
 
 module.exports.Algorithm = Algorithm
 module.exports.Claims = Claims
+module.exports.decodeHeader = decodeHeader
+module.exports.decodeUnverified = decodeUnverified
+module.exports.ErrorCode = ErrorCode
+module.exports.InMemoryReplayGuard = InMemoryReplayGuard
+module.exports.JtiFormat = JtiFormat
+module.exports.JweClient = JweClient
+module.exports.JwksClient = JwksClient
 module.exports.JwtClient = JwtClient
 module.exports.JwtCacheClient = JwtCacheClient
+module.exports.JwtKeyring = JwtKeyring
+module.exports.KeyManagement = KeyManagement
+module.exports.signFlattenedJws = signFlattenedJws
+module.exports.signGeneralJws = signGeneralJws
+module.exports.verifyFlattenedJws = verifyFlattenedJws
+module.exports.verifyGeneralJws = verifyGeneralJws
@@ -1,8 +1,10 @@
 use napi_derive::napi;
 
 #[napi(string_enum)]
+#[derive(Debug, Default, PartialEq, Eq, Hash)]
 pub enum Algorithm {
   /// HMAC using SHA-256
+  #[default]
   HS256,
   /// HMAC using SHA-384
   HS384,
@@ -28,12 +30,6 @@ pub enum Algorithm {
   EdDSA,
 }
 
-impl Default for Algorithm {
-  fn default() -> Self {
-    Self::HS256
-  }
-}
-
 impl From<Algorithm> for jsonwebtoken::Algorithm {
   #[inline]
   fn from(value: Algorithm) -> Self {
@@ -1,13 +1,56 @@
+use std::collections::BTreeMap;
+
+use napi::Either;
 use napi_derive::napi;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Number, Value};
 
+use crate::errors::{Error, ErrorCode};
+
+/// Claim names registered by RFC 7519 that `Claims` models as fields of its own
+const REGISTERED_CLAIMS: [&str; 7] = ["aud", "exp", "iat", "iss", "jti", "nbf", "sub"];
+
+/// (De)serializes the `aud` claim, which may be a single string or an array of strings
+mod audience {
+  use napi::Either;
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+  }
+
+  pub fn serialize<S: Serializer>(
+    aud: &Option<Either<String, Vec<String>>>,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    match aud {
+      Some(Either::A(single)) => single.serialize(serializer),
+      Some(Either::B(multiple)) => multiple.serialize(serializer),
+      None => serializer.serialize_none(),
+    }
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Option<Either<String, Vec<String>>>, D::Error> {
+    let aud = Option::<Audience>::deserialize(deserializer)?.map(|aud| match aud {
+      Audience::Single(single) => Either::A(single),
+      Audience::Multiple(multiple) => Either::B(multiple),
+    });
+
+    Ok(aud)
+  }
+}
+
 #[napi(object)]
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ClaimOpts {
-  /// Recipient for which the JWT is intended
-  #[serde(skip_serializing_if = "Option::is_none")]
-  pub aud: Option<String>,
+  /// Recipient(s) for which the JWT is intended
+  #[serde(default, with = "audience", skip_serializing_if = "Option::is_none")]
+  pub aud: Option<Either<String, Vec<String>>>,
   /// Time at which the JWT was issued (as UTC timestamp, seconds from epoch time)
   #[serde(skip_serializing_if = "Option::is_none")]
   pub iat: Option<Number>,
@@ -23,8 +66,83 @@ pub struct ClaimOpts {
   /// Subject of JWT (the user)
   #[serde(skip_serializing_if = "Option::is_none")]
   pub sub: Option<String>,
+  /// If set and `jti` is not given, a unique `jti` in this format is generated
+  #[serde(skip)]
+  pub generate_jti: Option<JtiFormat>,
+}
+
+/// How generated `jti` claims are formatted
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum JtiFormat {
+  /// Random UUID, e.g. `9b2f6b8e-3c1a-4d5e-8f7a-0b1c2d3e4f5a`
+  #[napi(value = "uuid-v4")]
+  UuidV4,
+  /// Time-ordered UUID, e.g. `019a1f4e-7b2c-7d3e-9f4a-5b6c7d8e9f0a`
+  #[napi(value = "uuid-v7")]
+  UuidV7,
+  /// Time-ordered ULID, e.g. `01JAB3M8Q4W5E6R7T8Y9V0X1Z2`
+  #[napi(value = "ulid")]
+  Ulid,
+}
+
+impl JtiFormat {
+  pub(crate) fn generate(self) -> String {
+    match self {
+      Self::UuidV4 => uuid::Uuid::new_v4().to_string(),
+      Self::UuidV7 => uuid::Uuid::now_v7().to_string(),
+      Self::Ulid => ulid::Ulid::new().to_string(),
+    }
+  }
+}
+
+/// Claims set on every token signed by a client, unless given in the per-call `ClaimOpts`
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct DefaultClaims {
+  /// Recipient(s) for which the JWT is intended
+  pub aud: Option<Either<String, Vec<String>>>,
+  /// Issuer of JWT
+  pub iss: Option<String>,
+  /// Whether to set `iat` to the time of signing.
+  ///
+  /// Defaults to `false`.
+  pub set_iat: Option<bool>,
+  /// If set, `nbf` is the time of signing plus this many seconds (negative values backdate it)
+  pub nbf_offset: Option<i64>,
+  /// If set, a unique `jti` in this format is generated for each token
+  pub generate_jti: Option<JtiFormat>,
 }
 
+impl DefaultClaims {
+  /// Per-call options, with the gaps filled in from these defaults
+  pub(crate) fn apply(&self, opts: Option<ClaimOpts>, now: u64) -> ClaimOpts {
+    let mut opts = opts.unwrap_or_default();
+
+    if opts.aud.is_none() {
+      opts.aud.clone_from(&self.aud);
+    }
+    if opts.iss.is_none() {
+      opts.iss.clone_from(&self.iss);
+    }
+    if opts.iat.is_none() && self.set_iat == Some(true) {
+      opts.iat = Some(Number::from(now));
+    }
+    if let (None, Some(offset)) = (&opts.nbf, self.nbf_offset) {
+      opts.nbf = Some(Number::from(now.saturating_add_signed(offset)));
+    }
+    if opts.generate_jti.is_none() {
+      opts.generate_jti = self.generate_jti;
+    }
+
+    opts
+  }
+}
+
+/// Verified claims: `Claims`, or for clients with flat claims a plain object with the custom claims
+/// beside the registered ones
+pub type VerifiedClaims = Either<Claims, BTreeMap<String, Value>>;
+
 #[napi]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
@@ -32,9 +150,9 @@ pub struct Claims {
   /// Time after which the JWT expires (as UTC timestamp, seconds from epoch time)
   pub exp: Number,
 
-  /// Recipient for which the JWT is intended
-  #[serde(skip_serializing_if = "Option::is_none")]
-  pub aud: Option<String>,
+  /// Recipient(s) for which the JWT is intended
+  #[serde(default, with = "audience", skip_serializing_if = "Option::is_none")]
+  pub aud: Option<Either<String, Vec<String>>>,
   /// Time at which the JWT was issued (as UTC timestamp, seconds from epoch time)
   #[serde(skip_serializing_if = "Option::is_none")]
   pub iat: Option<Number>,
@@ -56,8 +174,60 @@ pub struct Claims {
 impl Claims {
   #[napi(constructor)]
   pub fn new(data: Map<String, Value>, expires_in_seconds: u32, opts: Option<ClaimOpts>) -> Self {
-    let exp_val = jsonwebtoken::get_current_timestamp() + u64::from(expires_in_seconds);
-    let exp = Number::from(exp_val);
+    Self::new_at(
+      jsonwebtoken::get_current_timestamp(),
+      data,
+      expires_in_seconds,
+      opts,
+    )
+  }
+
+  #[napi]
+  /// Scopes granted by the space-delimited `scope` claim and the `scp` claim
+  pub fn scopes(&self) -> Vec<String> {
+    ClaimSet::scopes(self)
+  }
+
+  #[napi]
+  /// Roles granted by the `roles` claim
+  pub fn roles(&self) -> Vec<String> {
+    ClaimSet::roles(self)
+  }
+
+  #[napi]
+  pub fn has_scopes(&self, scopes: Vec<String>) -> bool {
+    let granted = self.scopes();
+    scopes.iter().all(|scope| granted.contains(scope))
+  }
+
+  #[napi]
+  pub fn has_any_scope(&self, scopes: Vec<String>) -> bool {
+    let granted = self.scopes();
+    scopes.iter().any(|scope| granted.contains(scope))
+  }
+
+  #[napi]
+  pub fn has_roles(&self, roles: Vec<String>) -> bool {
+    let granted = self.roles();
+    roles.iter().all(|role| granted.contains(role))
+  }
+
+  #[napi]
+  pub fn has_any_role(&self, roles: Vec<String>) -> bool {
+    let granted = self.roles();
+    roles.iter().any(|role| granted.contains(role))
+  }
+}
+
+impl Claims {
+  /// Claims expiring `expires_in_seconds` after `now`
+  pub(crate) fn new_at(
+    now: u64,
+    data: Map<String, Value>,
+    expires_in_seconds: u32,
+    opts: Option<ClaimOpts>,
+  ) -> Self {
+    let exp = Number::from(now + u64::from(expires_in_seconds));
 
     let opts = opts.unwrap_or_default();
 
@@ -67,9 +237,159 @@ impl Claims {
       aud: opts.aud,
       iat: opts.iat,
       iss: opts.iss,
-      jti: opts.jti,
+      jti: opts
+        .jti
+        .or_else(|| opts.generate_jti.map(JtiFormat::generate)),
       nbf: opts.nbf,
       sub: opts.sub,
     }
   }
+
+  /// `exp` in whole seconds, rounded up so that a fractional `exp` is never cut short
+  pub(crate) fn exp_secs(&self) -> u64 {
+    ceil_secs(&self.exp)
+  }
+}
+
+/// A timestamp in whole seconds, rounded up
+#[inline]
+pub(crate) fn ceil_secs(secs: &Number) -> u64 {
+  secs
+    .as_u64()
+    .unwrap_or_else(|| secs.as_f64().map_or(0, |secs| secs.ceil() as u64))
+}
+
+/// Claims as seen by the validation, either decoded into `Claims` or straight from a payload
+pub(crate) trait ClaimSet {
+  /// Value of the claim with this name, registered or not
+  fn claim(&self, name: &str) -> Option<Value>;
+
+  fn scopes(&self) -> Vec<String> {
+    let mut scopes = string_list(self.claim("scope").as_ref());
+    scopes.extend(string_list(self.claim("scp").as_ref()));
+    scopes
+  }
+
+  fn roles(&self) -> Vec<String> {
+    string_list(self.claim("roles").as_ref())
+  }
+}
+
+impl ClaimSet for Claims {
+  #[inline]
+  fn claim(&self, name: &str) -> Option<Value> {
+    self.get(name)
+  }
+}
+
+impl ClaimSet for Map<String, Value> {
+  #[inline]
+  fn claim(&self, name: &str) -> Option<Value> {
+    self.get(name).cloned()
+  }
+}
+
+impl ClaimSet for BTreeMap<String, Value> {
+  #[inline]
+  fn claim(&self, name: &str) -> Option<Value> {
+    self.get(name).cloned()
+  }
+}
+
+impl ClaimSet for VerifiedClaims {
+  #[inline]
+  fn claim(&self, name: &str) -> Option<Value> {
+    match self {
+      Either::A(claims) => claims.claim(name),
+      Either::B(payload) => payload.claim(name),
+    }
+  }
+}
+
+/// Entries of a space-delimited string or an array of strings claim
+fn string_list(value: Option<&Value>) -> Vec<String> {
+  match value {
+    Some(Value::String(list)) => list.split_whitespace().map(str::to_owned).collect(),
+    Some(Value::Array(items)) => items
+      .iter()
+      .filter_map(Value::as_str)
+      .map(str::to_owned)
+      .collect(),
+    _ => vec![],
+  }
+}
+
+impl Claims {
+  /// Value of a registered claim, or else of the custom claim in `data` with that name
+  pub(crate) fn get(&self, name: &str) -> Option<Value> {
+    let string = |s: &Option<String>| s.clone().map(Value::String);
+    let number = |n: &Option<Number>| n.clone().map(Value::Number);
+
+    match name {
+      "exp" => Some(Value::Number(self.exp.clone())),
+      "iat" => number(&self.iat),
+      "nbf" => number(&self.nbf),
+      "iss" => string(&self.iss),
+      "jti" => string(&self.jti),
+      "sub" => string(&self.sub),
+      "aud" => self.aud.as_ref().map(|aud| match aud {
+        Either::A(single) => Value::String(single.clone()),
+        Either::B(multiple) => multiple.iter().cloned().map(Value::String).collect(),
+      }),
+      _ => self.data.get(name).cloned(),
+    }
+  }
+
+  /// Payload with the `data` entries placed beside the registered claims instead of nested
+  pub(crate) fn to_flat_payload(&self) -> Result<Map<String, Value>, Error> {
+    if let Some(key) = self
+      .data
+      .keys()
+      .find(|key| REGISTERED_CLAIMS.contains(&key.as_str()))
+    {
+      return Err(Error::invalid_claim(
+        ErrorCode::ReservedClaim,
+        key,
+        format!("`{key}` is a registered claim and cannot be used as a custom claim"),
+      ));
+    }
+
+    self.clone().into_flat_payload()
+  }
+
+  fn into_flat_payload(self) -> Result<Map<String, Value>, Error> {
+    let mut payload = match serde_json::to_value(&self) {
+      Ok(Value::Object(payload)) => payload,
+      Ok(_) => unreachable!("claims always serialize to an object"),
+      Err(e) => return Err(Error::Generic(e.to_string())),
+    };
+    payload.remove("data");
+    payload.extend(self.data);
+
+    Ok(payload)
+  }
+
+  /// Claims as returned once verified, merged into a flat payload for clients with flat claims
+  pub(crate) fn into_verified(self, flat_claims: bool) -> Result<VerifiedClaims, Error> {
+    if flat_claims {
+      let payload = self.into_flat_payload()?;
+      Ok(Either::B(payload.into_iter().collect()))
+    } else {
+      Ok(Either::A(self))
+    }
+  }
+
+  /// Inverse of `to_flat_payload`, collecting every unregistered claim into `data`
+  pub(crate) fn from_flat_payload(mut payload: Map<String, Value>) -> Result<Self, Error> {
+    let mut claims = Map::new();
+    for name in REGISTERED_CLAIMS {
+      if let Some(value) = payload.remove(name) {
+        claims.insert(name.to_owned(), value);
+      }
+    }
+    claims.insert("data".to_owned(), Value::Object(payload));
+
+    serde_json::from_value(Value::Object(claims))
+      .map_err(|e| Error::validation(ErrorCode::Malformed, e.to_string()))
+  }
 }
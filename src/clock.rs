@@ -0,0 +1,22 @@
+use napi_derive::napi;
+
+/// Time used instead of the system time when signing and verifying, e.g. to test expiry
+#[napi(object)]
+#[derive(Debug, Default, Clone)]
+pub struct Clock {
+  /// Fixed current time (as UTC timestamp, seconds from epoch time)
+  pub now: Option<i64>,
+  /// Seconds added to the current time, or to `now` if set
+  pub offset: Option<i64>,
+}
+
+impl Clock {
+  pub(crate) fn now(&self) -> u64 {
+    let now = match self.now {
+      Some(now) => u64::try_from(now).unwrap_or_default(),
+      None => jsonwebtoken::get_current_timestamp(),
+    };
+
+    now.saturating_add_signed(self.offset.unwrap_or_default())
+  }
+}
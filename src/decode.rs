@@ -0,0 +1,50 @@
+use napi_derive::napi;
+use serde_json::{Map, Value};
+
+use crate::errors::Error;
+use crate::header::Header;
+
+#[napi(object)]
+pub struct UnverifiedToken {
+  pub header: Header,
+  pub payload: Map<String, Value>,
+}
+
+#[napi]
+/// Decode the header of a token without verifying its signature
+pub fn decode_header(token: String) -> Result<Header, Error> {
+  let header = jsonwebtoken::decode_header(&token)?;
+
+  Header::try_from(&header)
+}
+
+#[napi]
+/// Decode the header and payload of a token WITHOUT verifying its signature or claims.
+///
+/// Only use this to inspect a token (e.g. to pick the client that should verify it), never to
+/// trust its contents.
+pub fn decode_unverified(token: String) -> Result<UnverifiedToken, Error> {
+  let token_data = decode_payload_unverified(&token)?;
+
+  Ok(UnverifiedToken {
+    header: Header::try_from(&token_data.header)?,
+    payload: token_data.claims,
+  })
+}
+
+pub(crate) fn decode_payload_unverified(
+  token: &str,
+) -> Result<jsonwebtoken::TokenData<Map<String, Value>>, Error> {
+  let mut validation = jsonwebtoken::Validation::default();
+  validation.insecure_disable_signature_validation();
+  validation.required_spec_claims.clear();
+  validation.validate_exp = false;
+  validation.validate_aud = false;
+
+  jsonwebtoken::decode::<Map<String, Value>>(
+    token,
+    &jsonwebtoken::DecodingKey::from_secret(&[]),
+    &validation,
+  )
+  .map_err(Error::from)
+}
@@ -1,24 +1,199 @@
-use napi::JsError;
+use napi::{Env, JsError};
+use napi_derive::napi;
+
+/// Stable codes set as the `code` property of every error thrown by this package
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+  /// The key could not be parsed for the selected algorithm
+  #[napi(value = "ERR_JWT_INVALID_KEY")]
+  InvalidKey,
+  /// The token is not a well formed JWT, or its payload does not have the expected shape
+  #[napi(value = "ERR_JWT_MALFORMED")]
+  Malformed,
+  /// The signature does not match the token's header and payload
+  #[napi(value = "ERR_JWT_INVALID_SIGNATURE")]
+  InvalidSignature,
+  /// The token's algorithm is not allowed by the validation, or does not fit the key
+  #[napi(value = "ERR_JWT_INVALID_ALGORITHM")]
+  InvalidAlgorithm,
+  /// The `exp` claim is in the past, or the token is older than the validation's `maxAge`
+  #[napi(value = "ERR_JWT_EXPIRED")]
+  Expired,
+  /// The `nbf` claim, or the `iat` claim if it is validated, is in the future
+  #[napi(value = "ERR_JWT_NOT_YET_VALID")]
+  NotYetValid,
+  /// The `iss` claim does not match the validation
+  #[napi(value = "ERR_JWT_INVALID_ISSUER")]
+  InvalidIssuer,
+  /// The `aud` claim does not match the validation
+  #[napi(value = "ERR_JWT_INVALID_AUDIENCE")]
+  InvalidAudience,
+  /// The `sub` claim does not match the validation
+  #[napi(value = "ERR_JWT_INVALID_SUBJECT")]
+  InvalidSubject,
+  /// A claim required by the validation is missing
+  #[napi(value = "ERR_JWT_MISSING_CLAIM")]
+  MissingClaim,
+  /// A claim does not have a value allowed by the validation
+  #[napi(value = "ERR_JWT_INVALID_CLAIM")]
+  InvalidClaim,
+  /// The `scope`, `scp` or `roles` claim does not grant what the validation requires
+  #[napi(value = "ERR_JWT_INSUFFICIENT_SCOPE")]
+  InsufficientScope,
+  /// The token's `jti` was already accepted once by the replay guard
+  #[napi(value = "ERR_JWT_REPLAYED")]
+  Replayed,
+  /// The token, its `jti` or its `sub` was revoked
+  #[napi(value = "ERR_JWT_REVOKED")]
+  Revoked,
+  /// A custom claim uses the name of a registered claim
+  #[napi(value = "ERR_JWT_RESERVED_CLAIM")]
+  ReservedClaim,
+  /// The encrypted token could not be decrypted with the key, or was tampered with
+  #[napi(value = "ERR_JWE_DECRYPTION_FAILED")]
+  DecryptionFailed,
+  /// No key in the set matches the token's `kid`
+  #[napi(value = "ERR_JWKS_NO_MATCHING_KEY")]
+  NoMatchingKey,
+  /// Anything else, e.g. a failure inside the crypto backend
+  #[napi(value = "ERR_JWT_GENERIC")]
+  Generic,
+}
+
+impl AsRef<str> for ErrorCode {
+  fn as_ref(&self) -> &str {
+    match self {
+      Self::InvalidKey => "ERR_JWT_INVALID_KEY",
+      Self::Malformed => "ERR_JWT_MALFORMED",
+      Self::InvalidSignature => "ERR_JWT_INVALID_SIGNATURE",
+      Self::InvalidAlgorithm => "ERR_JWT_INVALID_ALGORITHM",
+      Self::Expired => "ERR_JWT_EXPIRED",
+      Self::NotYetValid => "ERR_JWT_NOT_YET_VALID",
+      Self::InvalidIssuer => "ERR_JWT_INVALID_ISSUER",
+      Self::InvalidAudience => "ERR_JWT_INVALID_AUDIENCE",
+      Self::InvalidSubject => "ERR_JWT_INVALID_SUBJECT",
+      Self::MissingClaim => "ERR_JWT_MISSING_CLAIM",
+      Self::InvalidClaim => "ERR_JWT_INVALID_CLAIM",
+      Self::InsufficientScope => "ERR_JWT_INSUFFICIENT_SCOPE",
+      Self::Replayed => "ERR_JWT_REPLAYED",
+      Self::Revoked => "ERR_JWT_REVOKED",
+      Self::ReservedClaim => "ERR_JWT_RESERVED_CLAIM",
+      Self::DecryptionFailed => "ERR_JWE_DECRYPTION_FAILED",
+      Self::NoMatchingKey => "ERR_JWKS_NO_MATCHING_KEY",
+      Self::Generic => "ERR_JWT_GENERIC",
+    }
+  }
+}
 
 pub enum Error {
   InvalidKey(String),
-  TokenValidationFailed(String),
+  TokenValidationFailed {
+    code: ErrorCode,
+    message: String,
+    /// Name of the offending claim, if the failure is about a single claim
+    claim: Option<String>,
+  },
+  UnknownKeyId(String),
   Generic(String),
 }
 
-impl From<Error> for napi::Error {
+impl Error {
+  #[inline]
+  pub(crate) fn validation(code: ErrorCode, message: impl Into<String>) -> Self {
+    Self::TokenValidationFailed {
+      code,
+      message: message.into(),
+      claim: None,
+    }
+  }
+
+  #[inline]
+  pub(crate) fn invalid_claim(
+    code: ErrorCode,
+    claim: impl Into<String>,
+    message: impl Into<String>,
+  ) -> Self {
+    Self::TokenValidationFailed {
+      code,
+      message: message.into(),
+      claim: Some(claim.into()),
+    }
+  }
+
+  pub fn code(&self) -> ErrorCode {
+    match self {
+      Self::InvalidKey(_) => ErrorCode::InvalidKey,
+      Self::TokenValidationFailed { code, .. } => *code,
+      Self::UnknownKeyId(_) => ErrorCode::NoMatchingKey,
+      Self::Generic(_) => ErrorCode::Generic,
+    }
+  }
+
+  pub fn claim(&self) -> Option<&str> {
+    match self {
+      Self::TokenValidationFailed { claim, .. } => claim.as_deref(),
+      _ => None,
+    }
+  }
+
+  pub fn message(&self) -> String {
+    match self {
+      Self::InvalidKey(msg) | Self::Generic(msg) => msg.to_owned(),
+      Self::TokenValidationFailed { message, .. } => message.to_owned(),
+      Self::UnknownKeyId(kid) => format!("No key found for kid `{kid}`"),
+    }
+  }
+
+  /// Build the JS error to throw, with `code` and (when known) `claim` set on it
+  pub(crate) fn into_js_error(self, env: Env) -> napi::Error {
+    let claim = self.claim().map(str::to_owned);
+    let js_error = JsError::from(self).into_unknown(env);
+
+    let Some(claim) = claim else {
+      return js_error.into();
+    };
+
+    match js_error.coerce_to_object() {
+      Ok(mut obj) => {
+        // Only fails if the engine is shutting down, and then nobody will read it anyway
+        let _ = obj.set_named_property("claim", claim);
+        obj.into_unknown().into()
+      }
+      Err(e) => e,
+    }
+  }
+}
+
+impl From<Error> for napi::Error<ErrorCode> {
   fn from(value: Error) -> Self {
-    match value {
-      Error::InvalidKey(e) => Self::new(napi::Status::InvalidArg, e),
-      Error::TokenValidationFailed(e) => Self::new(napi::Status::GenericFailure, e),
-      Error::Generic(msg) => Self::new(napi::Status::Unknown, msg),
+    Self::new(value.code(), value.message())
+  }
+}
+
+/// Error details for results that are returned instead of thrown
+#[napi(object)]
+pub struct ErrorInfo {
+  /// Same as the `code` of the equivalent thrown error
+  pub code: ErrorCode,
+  pub message: String,
+  /// Name of the offending claim, if the failure is about a single claim
+  pub claim: Option<String>,
+}
+
+impl From<Error> for ErrorInfo {
+  fn from(value: Error) -> Self {
+    Self {
+      code: value.code(),
+      message: value.message(),
+      claim: value.claim().map(str::to_owned),
     }
   }
 }
 
-impl From<Error> for JsError {
+impl From<Error> for JsError<ErrorCode> {
   fn from(value: Error) -> Self {
-    let err: napi::Error = value.into();
+    let err: napi::Error<ErrorCode> = value.into();
 
     err.into()
   }
@@ -33,15 +208,21 @@ impl From<jsonwebtoken::errors::Error> for Error {
       ErrorKind::InvalidRsaKey(_) | ErrorKind::InvalidEcdsaKey | ErrorKind::InvalidKeyFormat => {
         Self::InvalidKey(msg)
       }
-      ErrorKind::InvalidToken
-      | ErrorKind::InvalidSignature
-      | ErrorKind::ExpiredSignature
-      | ErrorKind::InvalidIssuer
-      | ErrorKind::MissingRequiredClaim(_)
-      | ErrorKind::InvalidAlgorithm
-      | ErrorKind::InvalidAudience
-      | ErrorKind::InvalidSubject
-      | ErrorKind::ImmatureSignature => Self::TokenValidationFailed(msg),
+      ErrorKind::InvalidToken | ErrorKind::Base64(_) | ErrorKind::Json(_) | ErrorKind::Utf8(_) => {
+        Self::validation(ErrorCode::Malformed, msg)
+      }
+      ErrorKind::InvalidSignature => Self::validation(ErrorCode::InvalidSignature, msg),
+      ErrorKind::InvalidAlgorithm
+      | ErrorKind::MissingAlgorithm
+      | ErrorKind::InvalidAlgorithmName => Self::validation(ErrorCode::InvalidAlgorithm, msg),
+      ErrorKind::ExpiredSignature => Self::invalid_claim(ErrorCode::Expired, "exp", msg),
+      ErrorKind::ImmatureSignature => Self::invalid_claim(ErrorCode::NotYetValid, "nbf", msg),
+      ErrorKind::InvalidIssuer => Self::invalid_claim(ErrorCode::InvalidIssuer, "iss", msg),
+      ErrorKind::InvalidAudience => Self::invalid_claim(ErrorCode::InvalidAudience, "aud", msg),
+      ErrorKind::InvalidSubject => Self::invalid_claim(ErrorCode::InvalidSubject, "sub", msg),
+      ErrorKind::MissingRequiredClaim(claim) => {
+        Self::invalid_claim(ErrorCode::MissingClaim, claim, msg)
+      }
       _ => Self::Generic(msg),
     }
   }
@@ -1,6 +1,111 @@
 use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 
 use crate::algorithm::Algorithm;
+use crate::errors::Error;
+
+#[napi(object)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Jwk {
+  /// Key type: `RSA`, `EC`, `OKP` or `oct`
+  ///
+  /// Defined in [RFC7517#4.1](https://tools.ietf.org/html/rfc7517#section-4.1).
+  #[serde(rename = "kty")]
+  pub key_type: String,
+
+  /// Intended use of the public key: `sig` or `enc`
+  ///
+  /// Defined in [RFC7517#4.2](https://tools.ietf.org/html/rfc7517#section-4.2).
+  #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+  pub public_key_use: Option<String>,
+
+  /// Operations the key is intended to be used for
+  ///
+  /// Defined in [RFC7517#4.3](https://tools.ietf.org/html/rfc7517#section-4.3).
+  #[serde(rename = "key_ops", skip_serializing_if = "Option::is_none")]
+  pub key_operations: Option<Vec<String>>,
+
+  /// Algorithm intended for use with the key
+  ///
+  /// Defined in [RFC7517#4.4](https://tools.ietf.org/html/rfc7517#section-4.4).
+  #[serde(rename = "alg", skip_serializing_if = "Option::is_none")]
+  pub key_algorithm: Option<String>,
+
+  /// Key ID
+  ///
+  /// Defined in [RFC7517#4.5](https://tools.ietf.org/html/rfc7517#section-4.5).
+  #[serde(rename = "kid", skip_serializing_if = "Option::is_none")]
+  pub key_id: Option<String>,
+
+  /// X.509 URL
+  ///
+  /// Defined in [RFC7517#4.6](https://tools.ietf.org/html/rfc7517#section-4.6).
+  #[serde(rename = "x5u", skip_serializing_if = "Option::is_none")]
+  pub x5_url: Option<String>,
+
+  /// X.509 certificate chain. A Vec of base64 encoded ASN.1 DER certificates.
+  ///
+  /// Defined in [RFC7517#4.7](https://tools.ietf.org/html/rfc7517#section-4.7).
+  #[serde(rename = "x5c", skip_serializing_if = "Option::is_none")]
+  pub x5_cert_chain: Option<Vec<String>>,
+
+  /// X.509 SHA1 certificate thumbprint
+  ///
+  /// Defined in [RFC7517#4.8](https://tools.ietf.org/html/rfc7517#section-4.8).
+  #[serde(rename = "x5t", skip_serializing_if = "Option::is_none")]
+  pub x5_cert_thumbprint: Option<String>,
+
+  /// X.509 SHA256 certificate thumbprint
+  ///
+  /// Defined in [RFC7517#4.9](https://tools.ietf.org/html/rfc7517#section-4.9).
+  #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+  pub x5t_s256_cert_thumbprint: Option<String>,
+
+  /// Curve of an `EC` or `OKP` key (`P-256`, `P-384`, `Ed25519`, ...)
+  #[serde(rename = "crv", skip_serializing_if = "Option::is_none")]
+  pub curve: Option<String>,
+
+  /// Modulus of an `RSA` key (base64url encoded)
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub n: Option<String>,
+
+  /// Exponent of an `RSA` key (base64url encoded)
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub e: Option<String>,
+
+  /// X coordinate of an `EC` key, or public key of an `OKP` key (base64url encoded)
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub x: Option<String>,
+
+  /// Y coordinate of an `EC` key (base64url encoded)
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub y: Option<String>,
+
+  /// Key value of an `oct` key (base64url encoded)
+  #[serde(rename = "k", skip_serializing_if = "Option::is_none")]
+  pub value: Option<String>,
+}
+
+impl TryFrom<Jwk> for jsonwebtoken::jwk::Jwk {
+  type Error = Error;
+
+  fn try_from(value: Jwk) -> Result<Self, Self::Error> {
+    serde_json::to_value(value)
+      .and_then(serde_json::from_value)
+      .map_err(|e| Error::InvalidKey(format!("Invalid jwk: {e}")))
+  }
+}
+
+impl TryFrom<&jsonwebtoken::jwk::Jwk> for Jwk {
+  type Error = Error;
+
+  fn try_from(value: &jsonwebtoken::jwk::Jwk) -> Result<Self, Self::Error> {
+    serde_json::to_value(value)
+      .and_then(serde_json::from_value)
+      .map_err(|e| Error::InvalidKey(format!("Invalid jwk: {e}")))
+  }
+}
 
 #[napi(object)]
 #[derive(Default)]
@@ -14,6 +119,7 @@ pub struct Header {
   /// Content type
   ///
   /// Defined in [RFC7519#5.2](https://tools.ietf.org/html/rfc7519#section-5.2).
+  /// For nested JWTs it stays on the signed token, as the encrypted one always has `JWT`.
   pub content_type: Option<String>,
 
   /// JSON Key URL
@@ -24,8 +130,7 @@ pub struct Header {
   /// JSON Web Key
   ///
   /// Defined in [RFC7515#4.1.3](https://tools.ietf.org/html/rfc7515#section-4.1.3).
-  // TODO: support jwk
-  // pub jwk: Option<Jwk>,
+  pub jwk: Option<Jwk>,
 
   /// Key ID
   ///
@@ -55,10 +160,17 @@ pub struct Header {
   pub x5t_s256_cert_thumbprint: Option<String>,
 }
 
-impl From<Header> for jsonwebtoken::Header {
+impl TryFrom<Header> for jsonwebtoken::Header {
+  type Error = Error;
+
   #[inline]
-  fn from(value: Header) -> Self {
-    jsonwebtoken::Header {
+  fn try_from(value: Header) -> Result<Self, Self::Error> {
+    let jwk = value
+      .jwk
+      .map(jsonwebtoken::jwk::Jwk::try_from)
+      .transpose()?;
+
+    Ok(jsonwebtoken::Header {
       typ: Some(String::from("JWT")),
       alg: value.algorithm.unwrap_or(Algorithm::HS256).into(),
       cty: value.content_type,
@@ -68,14 +180,52 @@ impl From<Header> for jsonwebtoken::Header {
       x5c: value.x5_cert_chain,
       x5t: value.x5_cert_thumbprint,
       x5t_s256: value.x5t_s256_cert_thumbprint,
-      jwk: None,
+      jwk,
+    })
+  }
+}
+
+impl Header {
+  /// The parameters that are set, under their registered names
+  pub(crate) fn to_params(&self) -> Map<String, Value> {
+    let mut params = Map::new();
+    if let Some(alg) = self.algorithm {
+      params.insert("alg".to_owned(), json!(jsonwebtoken::Algorithm::from(alg)));
+    }
+    if let Some(cty) = &self.content_type {
+      params.insert("cty".to_owned(), json!(cty));
+    }
+    if let Some(jku) = &self.json_key_url {
+      params.insert("jku".to_owned(), json!(jku));
+    }
+    if let Some(jwk) = &self.jwk {
+      params.insert("jwk".to_owned(), json!(jwk));
+    }
+    if let Some(kid) = &self.key_id {
+      params.insert("kid".to_owned(), json!(kid));
     }
+    if let Some(x5u) = &self.x5_url {
+      params.insert("x5u".to_owned(), json!(x5u));
+    }
+    if let Some(x5c) = &self.x5_cert_chain {
+      params.insert("x5c".to_owned(), json!(x5c));
+    }
+    if let Some(x5t) = &self.x5_cert_thumbprint {
+      params.insert("x5t".to_owned(), json!(x5t));
+    }
+    if let Some(x5t_s256) = &self.x5t_s256_cert_thumbprint {
+      params.insert("x5t#S256".to_owned(), json!(x5t_s256));
+    }
+
+    params
   }
 }
 
-impl From<&jsonwebtoken::Header> for Header {
-  fn from(value: &jsonwebtoken::Header) -> Self {
-    Self {
+impl TryFrom<&jsonwebtoken::Header> for Header {
+  type Error = Error;
+
+  fn try_from(value: &jsonwebtoken::Header) -> Result<Self, Self::Error> {
+    Ok(Self {
       x5t_s256_cert_thumbprint: value.x5t_s256.clone(),
       x5_cert_thumbprint: value.x5t.clone(),
       x5_cert_chain: value.x5c.clone(),
@@ -84,6 +234,7 @@ impl From<&jsonwebtoken::Header> for Header {
       content_type: value.cty.clone(),
       key_id: value.kid.clone(),
       json_key_url: value.jku.clone(),
-    }
+      jwk: value.jwk.as_ref().map(Jwk::try_from).transpose()?,
+    })
   }
 }
@@ -0,0 +1,480 @@
+use aws_lc_rs::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use aws_lc_rs::agreement::{self, ParsedPublicKey, UnparsedPublicKey, ECDH_P256};
+use aws_lc_rs::digest;
+use aws_lc_rs::key_wrap::{AesKek, KeyWrap, AES_256};
+use aws_lc_rs::rsa::{
+  OaepPrivateDecryptingKey, OaepPublicEncryptingKey, PrivateDecryptingKey, PublicEncryptingKey,
+  OAEP_SHA256_MGF1SHA256,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use napi::bindgen_prelude::Buffer;
+use napi::Either;
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{Error, ErrorCode};
+
+/// The only supported content encryption, AES GCM using a 256 bit key
+const ENC: &str = "A256GCM";
+const CEK_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+
+/// How the content encryption key of a JWE is agreed upon
+#[napi(string_enum)]
+#[derive(Debug)]
+pub enum KeyManagement {
+  /// The 256 bit shared key is used as the content encryption key
+  #[napi(value = "dir")]
+  Dir,
+  /// A random content encryption key, wrapped with the 256 bit shared key using AES Key Wrap
+  A256KW,
+  /// A random content encryption key, encrypted with the RSA public key using RSAES-OAEP and SHA-256
+  #[napi(value = "RSA-OAEP-256")]
+  RsaOaep256,
+  /// Content encryption key derived by ECDH between an ephemeral key and the P-256 public key
+  #[napi(value = "ECDH-ES")]
+  EcdhEs,
+}
+
+impl KeyManagement {
+  pub(crate) fn as_str(&self) -> &'static str {
+    match self {
+      Self::Dir => "dir",
+      Self::A256KW => "A256KW",
+      Self::RsaOaep256 => "RSA-OAEP-256",
+      Self::EcdhEs => "ECDH-ES",
+    }
+  }
+}
+
+/// Keys as accepted for each kind of key management
+pub(crate) enum JweKey {
+  Symmetric([u8; CEK_LEN]),
+  Rsa {
+    public: OaepPublicEncryptingKey,
+    private: OaepPrivateDecryptingKey,
+  },
+  Ec {
+    /// `SubjectPublicKeyInfo` DER, checked to be a P-256 key
+    public: Vec<u8>,
+    private: agreement::PrivateKey,
+  },
+}
+
+#[inline]
+fn invalid_key<E: std::fmt::Display>(kind: &str) -> impl Fn(E) -> Error + '_ {
+  move |e| Error::InvalidKey(format!("Invalid {kind} key: {e}"))
+}
+
+#[inline]
+fn parse_pem(key: &Either<String, Buffer>) -> Result<pem::Pem, Error> {
+  let pem = match key {
+    Either::A(s) => s.as_bytes(),
+    Either::B(buff) => buff,
+  };
+  pem::parse(pem).map_err(|_| Error::InvalidKey("Key is not a PEM encoded string".to_owned()))
+}
+
+/// `AlgorithmIdentifier` of `rsaEncryption`, with its NULL parameters
+const RSA_ALGORITHM_ID: &[u8] = &[
+  0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+];
+
+/// DER encoding of a value with this tag and contents
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+  let mut out = vec![tag];
+  let len = contents.len();
+  if len < 0x80 {
+    out.push(len as u8);
+  } else {
+    let len = len.to_be_bytes();
+    let len = &len[len.iter().take_while(|b| **b == 0).count()..];
+    out.push(0x80 | len.len() as u8);
+    out.extend(len);
+  }
+  out.extend(contents);
+  out
+}
+
+/// PKCS#1 `RSAPublicKey` as the `SubjectPublicKeyInfo` aws-lc-rs expects
+fn rsa_spki(pkcs1: &[u8]) -> Vec<u8> {
+  let bits = [&[0][..], pkcs1].concat();
+  der(0x30, &[RSA_ALGORITHM_ID, &der(0x03, &bits)].concat())
+}
+
+/// PKCS#1 `RSAPrivateKey` as the PKCS#8 `PrivateKeyInfo` aws-lc-rs expects
+fn rsa_pkcs8(pkcs1: &[u8]) -> Vec<u8> {
+  let version = [0x02, 0x01, 0x00];
+  der(
+    0x30,
+    &[&version, RSA_ALGORITHM_ID, &der(0x04, pkcs1)].concat(),
+  )
+}
+
+impl JweKey {
+  pub(crate) fn symmetric(secret_key: Either<String, Buffer>) -> Result<Self, Error> {
+    let bytes = match &secret_key {
+      Either::A(s) => s.as_bytes(),
+      Either::B(buff) => buff,
+    };
+    let key = bytes.try_into().map_err(|_| {
+      Error::InvalidKey(format!(
+        "{ENC} needs a 256 bit key, got {} bits",
+        bytes.len() * 8
+      ))
+    })?;
+
+    Ok(Self::Symmetric(key))
+  }
+
+  /// Keys in PKCS#8 / `SubjectPublicKeyInfo` or PKCS#1 PEM
+  pub(crate) fn rsa(
+    pub_key: Either<String, Buffer>,
+    priv_key: Either<String, Buffer>,
+  ) -> Result<Self, Error> {
+    let (pub_pem, priv_pem) = (parse_pem(&pub_key)?, parse_pem(&priv_key)?);
+    let public = match pub_pem.tag() {
+      "RSA PUBLIC KEY" => PublicEncryptingKey::from_der(&rsa_spki(pub_pem.contents())),
+      _ => PublicEncryptingKey::from_der(pub_pem.contents()),
+    }
+    .map_err(invalid_key("RSA"))?;
+    let private = match priv_pem.tag() {
+      "RSA PRIVATE KEY" => PrivateDecryptingKey::from_pkcs8(&rsa_pkcs8(priv_pem.contents())),
+      _ => PrivateDecryptingKey::from_pkcs8(priv_pem.contents()),
+    }
+    .map_err(invalid_key("RSA"))?;
+
+    Ok(Self::Rsa {
+      public: OaepPublicEncryptingKey::new(public).map_err(invalid_key("RSA"))?,
+      private: OaepPrivateDecryptingKey::new(private).map_err(invalid_key("RSA"))?,
+    })
+  }
+
+  /// Keys in PKCS#8 / `SubjectPublicKeyInfo` or SEC1 PEM
+  pub(crate) fn ec(
+    pub_key: Either<String, Buffer>,
+    priv_key: Either<String, Buffer>,
+  ) -> Result<Self, Error> {
+    let (pub_pem, priv_pem) = (parse_pem(&pub_key)?, parse_pem(&priv_key)?);
+    let public = pub_pem.into_contents();
+    ParsedPublicKey::try_from(UnparsedPublicKey::new(&ECDH_P256, &public))
+      .map_err(invalid_key("P-256"))?;
+    let private = agreement::PrivateKey::from_private_key_der(&ECDH_P256, priv_pem.contents())
+      .map_err(invalid_key("P-256"))?;
+
+    Ok(Self::Ec { public, private })
+  }
+}
+
+/// Public part of the ephemeral key of `ECDH-ES`, as a JWK
+#[derive(Serialize, Deserialize)]
+struct EphemeralKey {
+  kty: String,
+  crv: String,
+  x: String,
+  y: String,
+}
+
+/// JOSE header of a JWE
+#[derive(Serialize, Deserialize)]
+pub(crate) struct JweHeader {
+  pub(crate) alg: String,
+  pub(crate) enc: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) kid: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) typ: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) cty: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  epk: Option<EphemeralKey>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  apu: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  apv: Option<String>,
+  /// Neither compression nor critical extensions are supported, so tokens using them are rejected
+  #[serde(skip_serializing)]
+  zip: Option<String>,
+  #[serde(skip_serializing)]
+  crit: Option<Vec<String>>,
+}
+
+#[inline]
+fn malformed(msg: impl Into<String>) -> Error {
+  Error::validation(ErrorCode::Malformed, msg)
+}
+
+#[inline]
+fn decryption_failed() -> Error {
+  // The same error for every failure, so that they can't be told apart by an attacker
+  Error::validation(ErrorCode::DecryptionFailed, "Token could not be decrypted")
+}
+
+#[inline]
+fn b64_decode(part: &str) -> Result<Vec<u8>, Error> {
+  URL_SAFE_NO_PAD
+    .decode(part)
+    .map_err(|e| malformed(e.to_string()))
+}
+
+/// Content encryption key for `ECDH-ES`, per the Concat KDF of NIST SP 800-56A as profiled by
+/// RFC 7518 section 4.6.2
+fn concat_kdf(shared_secret: &[u8], apu: &[u8], apv: &[u8]) -> [u8; CEK_LEN] {
+  let mut hasher = digest::Context::new(&digest::SHA256);
+  hasher.update(&1u32.to_be_bytes());
+  hasher.update(shared_secret);
+  for field in [ENC.as_bytes(), apu, apv] {
+    hasher.update(&(field.len() as u32).to_be_bytes());
+    hasher.update(field);
+  }
+  hasher.update(&((CEK_LEN * 8) as u32).to_be_bytes());
+
+  let mut cek = [0; CEK_LEN];
+  cek.copy_from_slice(hasher.finish().as_ref());
+  cek
+}
+
+#[inline]
+fn random<const N: usize>() -> Result<[u8; N], Error> {
+  let mut bytes = [0; N];
+  aws_lc_rs::rand::fill(&mut bytes).map_err(|e| Error::Generic(e.to_string()))?;
+  Ok(bytes)
+}
+
+/// AES GCM with the content encryption key
+#[inline]
+fn content_cipher(cek: &[u8; CEK_LEN]) -> Result<LessSafeKey, Error> {
+  UnboundKey::new(&AES_256_GCM, cek)
+    .map(LessSafeKey::new)
+    .map_err(|e| Error::Generic(e.to_string()))
+}
+
+/// Content encryption key of a new token, with what the recipient needs to recover it
+struct ContentKey {
+  cek: [u8; CEK_LEN],
+  encrypted_key: Vec<u8>,
+  /// Only for `ECDH-ES`
+  epk: Option<EphemeralKey>,
+}
+
+/// Everything needed to encrypt and decrypt compact JWEs
+pub(crate) struct Jwe {
+  pub(crate) key_management: KeyManagement,
+  pub(crate) key: JweKey,
+  pub(crate) key_id: Option<String>,
+}
+
+impl Jwe {
+  fn new_cek(&self) -> Result<ContentKey, Error> {
+    let generic = |e: aws_lc_rs::error::Unspecified| Error::Generic(e.to_string());
+
+    match (&self.key_management, &self.key) {
+      (KeyManagement::Dir, JweKey::Symmetric(key)) => Ok(ContentKey {
+        cek: *key,
+        encrypted_key: vec![],
+        epk: None,
+      }),
+      (KeyManagement::A256KW, JweKey::Symmetric(key)) => {
+        let cek = random()?;
+        // Key Wrap adds a 64 bit integrity check value
+        let mut wrapped = vec![0; CEK_LEN + 8];
+        AesKek::new(&AES_256, key)
+          .and_then(|kek| kek.wrap(&cek, &mut wrapped).map(|_| ()))
+          .map_err(generic)?;
+        Ok(ContentKey {
+          cek,
+          encrypted_key: wrapped,
+          epk: None,
+        })
+      }
+      (KeyManagement::RsaOaep256, JweKey::Rsa { public, .. }) => {
+        let cek = random()?;
+        let mut encrypted = vec![0; public.ciphertext_size()];
+        let len = public
+          .encrypt(&OAEP_SHA256_MGF1SHA256, &cek, &mut encrypted, None)
+          .map_err(generic)?
+          .len();
+        encrypted.truncate(len);
+        Ok(ContentKey {
+          cek,
+          encrypted_key: encrypted,
+          epk: None,
+        })
+      }
+      (KeyManagement::EcdhEs, JweKey::Ec { public, .. }) => {
+        let ephemeral = agreement::PrivateKey::generate(&ECDH_P256).map_err(generic)?;
+        let cek = agreement::agree(
+          &ephemeral,
+          UnparsedPublicKey::new(&ECDH_P256, public),
+          Error::Generic("Key agreement failed".to_owned()),
+          |shared| Ok(concat_kdf(shared, &[], &[])),
+        )?;
+
+        // Uncompressed SEC1 point, `0x04 || x || y`
+        let point = ephemeral.compute_public_key().map_err(generic)?;
+        let (x, y) = point.as_ref()[1..].split_at(32);
+        let epk = EphemeralKey {
+          kty: "EC".to_owned(),
+          crv: "P-256".to_owned(),
+          x: URL_SAFE_NO_PAD.encode(x),
+          y: URL_SAFE_NO_PAD.encode(y),
+        };
+        Ok(ContentKey {
+          cek,
+          encrypted_key: vec![],
+          epk: Some(epk),
+        })
+      }
+      (key_management, _) => Err(Error::InvalidKey(format!(
+        "Key does not fit {}",
+        key_management.as_str()
+      ))),
+    }
+  }
+
+  fn recover_cek(&self, header: &JweHeader, encrypted_key: &[u8]) -> Result<[u8; CEK_LEN], Error> {
+    match (&self.key_management, &self.key) {
+      (KeyManagement::Dir, JweKey::Symmetric(key)) if encrypted_key.is_empty() => Ok(*key),
+      (KeyManagement::A256KW, JweKey::Symmetric(key)) => {
+        let mut cek = [0; CEK_LEN];
+        AesKek::new(&AES_256, key)
+          .and_then(|kek| kek.unwrap(encrypted_key, &mut cek).map(|cek| cek.len()))
+          .ok()
+          .filter(|len| *len == CEK_LEN)
+          .map(|_| cek)
+          .ok_or_else(decryption_failed)
+      }
+      (KeyManagement::RsaOaep256, JweKey::Rsa { private, .. }) => {
+        let mut cek = vec![0; private.min_output_size()];
+        private
+          .decrypt(&OAEP_SHA256_MGF1SHA256, encrypted_key, &mut cek, None)
+          .ok()
+          .and_then(|cek| <[u8; CEK_LEN]>::try_from(&*cek).ok())
+          .ok_or_else(decryption_failed)
+      }
+      (KeyManagement::EcdhEs, JweKey::Ec { private, .. }) if encrypted_key.is_empty() => {
+        let epk = header
+          .epk
+          .as_ref()
+          .filter(|epk| epk.kty == "EC" && epk.crv == "P-256")
+          .ok_or_else(|| malformed("Missing or unsupported `epk` header"))?;
+        let (x, y) = (b64_decode(&epk.x)?, b64_decode(&epk.y)?);
+        if x.len() != 32 || y.len() != 32 {
+          return Err(malformed("Invalid `epk` header"));
+        }
+        let point = [&[0x04][..], &x, &y].concat();
+        let apu = header.apu.as_deref().map(b64_decode).transpose()?;
+        let apv = header.apv.as_deref().map(b64_decode).transpose()?;
+
+        // Fails unless the point is on the curve
+        agreement::agree(
+          private,
+          UnparsedPublicKey::new(&ECDH_P256, &point),
+          malformed("Invalid `epk` header"),
+          |shared| {
+            Ok(concat_kdf(
+              shared,
+              apu.as_deref().unwrap_or_default(),
+              apv.as_deref().unwrap_or_default(),
+            ))
+          },
+        )
+      }
+      (KeyManagement::Dir | KeyManagement::EcdhEs, _) => Err(malformed(
+        "Encrypted key must be empty for direct key agreement",
+      )),
+      (key_management, _) => Err(Error::InvalidKey(format!(
+        "Key does not fit {}",
+        key_management.as_str()
+      ))),
+    }
+  }
+
+  /// Encrypt `plaintext` into a compact JWE, with `cty` describing the plaintext if given
+  pub(crate) fn encrypt(&self, plaintext: &[u8], cty: Option<&str>) -> Result<String, Error> {
+    let ContentKey {
+      cek,
+      encrypted_key,
+      epk,
+    } = self.new_cek()?;
+    let header = JweHeader {
+      alg: self.key_management.as_str().to_owned(),
+      enc: ENC.to_owned(),
+      kid: self.key_id.clone(),
+      typ: Some("JWT".to_owned()),
+      cty: cty.map(str::to_owned),
+      epk,
+      apu: None,
+      apv: None,
+      zip: None,
+      crit: None,
+    };
+    let encoded_header = URL_SAFE_NO_PAD
+      .encode(serde_json::to_vec(&header).map_err(|e| Error::Generic(e.to_string()))?);
+
+    let iv = random::<NONCE_LEN>()?;
+    let mut ciphertext = plaintext.to_vec();
+    let tag = content_cipher(&cek)?
+      .seal_in_place_separate_tag(
+        Nonce::assume_unique_for_key(iv),
+        Aad::from(encoded_header.as_bytes()),
+        &mut ciphertext,
+      )
+      .map_err(|e| Error::Generic(e.to_string()))?;
+
+    Ok(format!(
+      "{encoded_header}.{}.{}.{}.{}",
+      URL_SAFE_NO_PAD.encode(encrypted_key),
+      URL_SAFE_NO_PAD.encode(iv),
+      URL_SAFE_NO_PAD.encode(ciphertext),
+      URL_SAFE_NO_PAD.encode(tag),
+    ))
+  }
+
+  /// Decrypt a compact JWE, returning its header and plaintext
+  pub(crate) fn decrypt(&self, token: &str) -> Result<(JweHeader, Vec<u8>), Error> {
+    let parts: Vec<&str> = token.split('.').collect();
+    let [encoded_header, encrypted_key, iv, ciphertext, tag] = parts[..] else {
+      return Err(malformed("A compact JWE must have 5 parts"));
+    };
+
+    let header: JweHeader =
+      serde_json::from_slice(&b64_decode(encoded_header)?).map_err(|e| malformed(e.to_string()))?;
+    if header.alg != self.key_management.as_str() || header.enc != ENC {
+      return Err(Error::validation(
+        ErrorCode::InvalidAlgorithm,
+        format!(
+          "Expected {} with {ENC}, got {} with {}",
+          self.key_management.as_str(),
+          header.alg,
+          header.enc
+        ),
+      ));
+    }
+    if header.zip.is_some() || header.crit.is_some() {
+      return Err(malformed("`zip` and `crit` headers are not supported"));
+    }
+
+    let cek = self.recover_cek(&header, &b64_decode(encrypted_key)?)?;
+    let iv = b64_decode(iv)?;
+    let iv = Nonce::try_assume_unique_for_key(&iv)
+      .map_err(|_| malformed("Initialization vector must be 96 bits"))?;
+    let tag = b64_decode(tag)?;
+    if tag.len() != TAG_LEN {
+      return Err(malformed("Authentication tag must be 128 bits"));
+    }
+    let mut plaintext = b64_decode(ciphertext)?;
+
+    content_cipher(&cek)?
+      .open_in_place_separate_tag(
+        iv,
+        Aad::from(encoded_header.as_bytes()),
+        &tag,
+        &mut plaintext,
+      )
+      .map_err(|_| decryption_failed())?;
+
+    Ok((header, plaintext))
+  }
+}
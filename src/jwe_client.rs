@@ -0,0 +1,234 @@
+use napi::bindgen_prelude::Buffer;
+use napi::{Either, Env};
+use napi_derive::napi;
+use serde_json::{Map, Value};
+
+use crate::claims::{ClaimOpts, Claims, VerifiedClaims};
+use crate::clock::Clock;
+use crate::errors::{Error, ErrorCode};
+use crate::jwe::{Jwe, JweHeader, JweKey, KeyManagement};
+use crate::jwt_client::JwtClient;
+use crate::validation::{check_registered, ClaimChecks, Validation};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct JweClientInitOpts {
+  /// Defaults to `dir` for a shared key, and to `RSA-OAEP-256` for a key pair
+  pub key_management: Option<KeyManagement>,
+  /// `kid` header of encrypted tokens
+  pub key_id: Option<String>,
+  pub validation: Option<Validation>,
+  /// Put custom claims beside `exp`, `iss` etc. instead of under a `data` claim. Decrypted claims
+  /// are then returned the same way, as a plain object rather than `Claims`.
+  ///
+  /// Defaults to `false`.
+  pub flat_claims: Option<bool>,
+  /// Time used for `exp` when encrypting, and for checking `exp`, `nbf` and `iat` when
+  /// decrypting. Defaults to the system time.
+  pub clock: Option<Clock>,
+}
+
+/// Client for encrypted tokens, as compact JWE using `A256GCM` content encryption
+#[napi]
+pub struct JweClient {
+  jwe: Jwe,
+  validation: jsonwebtoken::Validation,
+  claim_checks: ClaimChecks,
+  flat_claims: bool,
+  clock: Clock,
+}
+
+impl JweClient {
+  fn from_parts(key_management: KeyManagement, key: JweKey, opts: JweClientInitOpts) -> Self {
+    let (validation, claim_checks) = opts
+      .validation
+      .unwrap_or_default()
+      .into_parts(jsonwebtoken::Algorithm::default());
+
+    Self {
+      jwe: Jwe {
+        key_management,
+        key,
+        key_id: opts.key_id,
+      },
+      validation,
+      claim_checks,
+      flat_claims: opts.flat_claims.unwrap_or_default(),
+      clock: opts.clock.unwrap_or_default(),
+    }
+  }
+
+  fn decrypt_inner(&self, token: &str) -> Result<Claims, Error> {
+    let (header, plaintext) = self.jwe.decrypt(token)?;
+    if is_nested(&header) {
+      return Err(Error::validation(
+        ErrorCode::Malformed,
+        "Token is a nested JWT, use `decryptAndVerify`",
+      ));
+    }
+    let payload: Map<String, Value> = serde_json::from_slice(&plaintext)
+      .map_err(|e| Error::validation(ErrorCode::Malformed, e.to_string()))?;
+    let claims = if self.flat_claims {
+      Claims::from_flat_payload(payload)?
+    } else {
+      serde_json::from_value(Value::Object(payload))
+        .map_err(|e| Error::validation(ErrorCode::Malformed, e.to_string()))?
+    };
+
+    let now = self.clock.now();
+    check_registered(&claims, &self.validation, now)?;
+    self.claim_checks.check(&claims)?;
+    self
+      .claim_checks
+      .check_iat(&claims, now, self.validation.leeway)?;
+
+    Ok(claims)
+  }
+
+  fn decrypt_and_verify_inner(
+    &self,
+    env: Env,
+    verifier: &JwtClient,
+    token: &str,
+    validation: Option<Validation>,
+  ) -> Result<VerifiedClaims, Error> {
+    let (header, plaintext) = self.jwe.decrypt(token)?;
+    if !is_nested(&header) {
+      return Err(Error::validation(
+        ErrorCode::Malformed,
+        "Token is not a nested JWT, expected a `cty` header of `JWT`",
+      ));
+    }
+    let inner = String::from_utf8(plaintext)
+      .map_err(|e| Error::validation(ErrorCode::Malformed, e.to_string()))?;
+
+    let verifier = verifier.verifier.layered(validation);
+    let claims = verifier.verify(&inner)?.claims;
+    verifier.check_replay(env, &claims)?;
+
+    claims.into_verified(verifier.flat_claims)
+  }
+}
+
+/// `cty` of a JWE whose plaintext is a signed JWT, per RFC 7519 section 5.2
+const NESTED_CTY: &str = "JWT";
+
+/// Whether the plaintext is a signed JWT, whatever the signed token's own `cty`
+#[inline]
+fn is_nested(header: &JweHeader) -> bool {
+  header
+    .cty
+    .as_deref()
+    .is_some_and(|cty| cty.eq_ignore_ascii_case(NESTED_CTY))
+}
+
+#[napi]
+impl JweClient {
+  #[napi(constructor)]
+  /// For `dir` and `A256KW`, with a 256 bit shared key
+  pub fn new(
+    secret_key: Either<String, Buffer>,
+    opts: Option<JweClientInitOpts>,
+  ) -> Result<Self, Error> {
+    let opts = opts.unwrap_or_default();
+    let key_management = opts.key_management.unwrap_or(KeyManagement::Dir);
+    if !matches!(key_management, KeyManagement::Dir | KeyManagement::A256KW) {
+      return Err(Error::InvalidKey(format!(
+        "{} needs a key pair, use `withPubPrivKeys`",
+        key_management.as_str()
+      )));
+    }
+
+    let key = JweKey::symmetric(secret_key)?;
+    Ok(Self::from_parts(key_management, key, opts))
+  }
+
+  #[napi(factory)]
+  /// For `RSA-OAEP-256` with an RSA key pair, and `ECDH-ES` with a P-256 key pair
+  pub fn with_pub_priv_keys(
+    pub_key: Either<String, Buffer>,
+    priv_key: Either<String, Buffer>,
+    opts: Option<JweClientInitOpts>,
+  ) -> Result<Self, Error> {
+    let opts = opts.unwrap_or_default();
+    let key_management = opts.key_management.unwrap_or(KeyManagement::RsaOaep256);
+    let key = match key_management {
+      KeyManagement::RsaOaep256 => JweKey::rsa(pub_key, priv_key)?,
+      KeyManagement::EcdhEs => JweKey::ec(pub_key, priv_key)?,
+      KeyManagement::Dir | KeyManagement::A256KW => {
+        return Err(Error::InvalidKey(format!(
+          "{} needs a shared key, use the constructor",
+          key_management.as_str()
+        )))
+      }
+    };
+
+    Ok(Self::from_parts(key_management, key, opts))
+  }
+
+  #[napi]
+  pub fn encrypt(
+    &self,
+    data: Map<String, Value>,
+    expires_in_seconds: u32,
+    claim_opts: Option<ClaimOpts>,
+  ) -> Result<String, Error> {
+    let claims = Claims::new_at(self.clock.now(), data, expires_in_seconds, claim_opts);
+    self.encrypt_claims(&claims)
+  }
+
+  #[napi]
+  pub fn encrypt_claims(&self, claims: &Claims) -> Result<String, Error> {
+    let payload = if self.flat_claims {
+      serde_json::to_vec(&claims.to_flat_payload()?)
+    } else {
+      serde_json::to_vec(claims)
+    }
+    .map_err(|e| Error::Generic(e.to_string()))?;
+
+    self.jwe.encrypt(&payload, None)
+  }
+
+  #[napi]
+  /// Sign the claims with `signer`, then encrypt the signed token as a nested JWT. The signer's
+  /// header, `contentType` included, describes the signed token and is kept on it, while the JWE
+  /// header gets a `cty` of `JWT` (RFC 7519 section 5.2) so that the nesting can be told apart.
+  pub fn sign_and_encrypt(
+    &self,
+    signer: &JwtClient,
+    data: Map<String, Value>,
+    expires_in_seconds: u32,
+    claim_opts: Option<ClaimOpts>,
+  ) -> Result<String, Error> {
+    let claims = signer.signer.claims(data, expires_in_seconds, claim_opts);
+    let token = signer.signer.sign(&claims)?;
+
+    self.jwe.encrypt(token.as_bytes(), Some(NESTED_CTY))
+  }
+
+  #[napi]
+  /// Decrypt a nested JWT, then verify the signed token inside with `verifier` and its validation,
+  /// with `validation` layered over it for this call only
+  pub fn decrypt_and_verify(
+    &self,
+    env: Env,
+    verifier: &JwtClient,
+    token: String,
+    validation: Option<Validation>,
+  ) -> napi::Result<VerifiedClaims> {
+    let claims = self
+      .decrypt_and_verify_inner(env, verifier, &token, validation)
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(claims)
+  }
+
+  #[napi]
+  /// Decrypt a token and validate its claims
+  pub fn decrypt(&self, env: Env, token: String) -> napi::Result<VerifiedClaims> {
+    let claims = self
+      .decrypt_inner(&token)
+      .and_then(|claims| claims.into_verified(self.flat_claims))
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(claims)
+  }
+}
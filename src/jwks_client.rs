@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use jsonwebtoken::jwk::Jwk;
+use napi::Env;
+use napi_derive::napi;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::claims::Claims;
+use crate::errors::{Error, ErrorCode};
+use crate::keys::{get_jwk_algorithms, get_jwk_decoding_key, KeyFamily};
+use crate::validation::{ClaimChecks, Validation};
+use crate::verifier::Verifier;
+
+#[derive(Deserialize)]
+struct RawJwkSet {
+  keys: Vec<Value>,
+}
+
+/// Key of the set that can't be used for verification, and why
+#[napi(object)]
+#[derive(Clone)]
+pub struct SkippedJwk {
+  pub kid: Option<String>,
+  pub reason: String,
+}
+
+/// Verifier backed by a JWK Set, selecting the key by the token's `kid` header
+#[napi]
+pub struct JwksClient {
+  /// Keys sharing a `kid` are all kept, in the order of the set
+  keys: HashMap<String, Vec<Verifier>>,
+  skipped: Vec<SkippedJwk>,
+}
+
+/// Verifier for one key of the set, or the reason it is skipped
+fn key_verifier(
+  jwk: &Jwk,
+  allowed_algs: Option<&[jsonwebtoken::Algorithm]>,
+  base_validation: &jsonwebtoken::Validation,
+  claim_checks: &ClaimChecks,
+) -> Result<Verifier, String> {
+  let mut algorithms = get_jwk_algorithms(jwk);
+  if algorithms.is_empty() {
+    return Err(
+      "Encryption key, unsupported key type or `alg` not matching the key type".to_owned(),
+    );
+  }
+  if let Some(allowed) = allowed_algs {
+    algorithms.retain(|alg| allowed.contains(alg));
+  }
+  if algorithms.is_empty() {
+    return Err("None of the key's algorithms is allowed by the validation".to_owned());
+  }
+
+  let mut validation = base_validation.clone();
+  validation.algorithms = algorithms;
+
+  Ok(Verifier {
+    decoding_key: Arc::new(get_jwk_decoding_key(jwk).map_err(|e| e.message())?),
+    key_family: KeyFamily::from(&jwk.algorithm),
+    key_id: None,
+    previous_keys: Arc::new([]),
+    validation,
+    claim_checks: claim_checks.clone(),
+    flat_claims: false,
+    replay_guard: None,
+    revocations: None,
+    cache_scope: None,
+    clock: None,
+  })
+}
+
+impl JwksClient {
+  fn verify_inner(&self, token: &str) -> Result<Claims, Error> {
+    let header = jsonwebtoken::decode_header(token)?;
+    let kid = header
+      .kid
+      .ok_or_else(|| Error::validation(ErrorCode::NoMatchingKey, "Token header has no `kid`"))?;
+    let verifiers = self.keys.get(&kid).ok_or(Error::UnknownKeyId(kid))?;
+
+    let mut error = None;
+    for verifier in verifiers {
+      match verifier.verify(token) {
+        Ok(token_data) => return Ok(token_data.claims),
+        // Another key with the same `kid` may still match
+        Err(e)
+          if matches!(
+            e.code(),
+            ErrorCode::InvalidSignature | ErrorCode::InvalidAlgorithm
+          ) =>
+        {
+          if error.is_none() || e.code() != ErrorCode::InvalidAlgorithm {
+            error = Some(e);
+          }
+        }
+        Err(e) => return Err(e),
+      }
+    }
+
+    Err(
+      error
+        .unwrap_or_else(|| Error::validation(ErrorCode::NoMatchingKey, "No key matches the token")),
+    )
+  }
+}
+
+#[napi]
+impl JwksClient {
+  #[napi(constructor)]
+  /// Keys that can't be used for verification (malformed, without a `kid`, encryption keys,
+  /// unsupported key types) are skipped and listed in `skippedKeys`. Keys sharing a `kid` are all
+  /// kept and tried in order.
+  pub fn new(jwks: String, validation: Option<Validation>) -> Result<Self, Error> {
+    let jwk_set: RawJwkSet =
+      serde_json::from_str(&jwks).map_err(|e| Error::InvalidKey(e.to_string()))?;
+
+    let validation = validation.unwrap_or_default();
+    let allowed_algs: Option<Vec<jsonwebtoken::Algorithm>> = validation
+      .algorithms
+      .as_ref()
+      .map(|algs| algs.iter().map(|alg| alg.to_owned().into()).collect());
+    let (base_validation, claim_checks) = validation.into_parts(jsonwebtoken::Algorithm::default());
+
+    let mut keys: HashMap<String, Vec<Verifier>> = HashMap::new();
+    let mut skipped = vec![];
+    for raw in jwk_set.keys {
+      let raw_kid = raw.get("kid").and_then(Value::as_str).map(str::to_owned);
+      let jwk = match serde_json::from_value::<Jwk>(raw) {
+        Ok(jwk) => jwk,
+        Err(e) => {
+          skipped.push(SkippedJwk {
+            kid: raw_kid,
+            reason: e.to_string(),
+          });
+          continue;
+        }
+      };
+      let Some(kid) = jwk.common.key_id.clone() else {
+        skipped.push(SkippedJwk {
+          kid: None,
+          reason: "Key has no `kid`".to_owned(),
+        });
+        continue;
+      };
+
+      match key_verifier(
+        &jwk,
+        allowed_algs.as_deref(),
+        &base_validation,
+        &claim_checks,
+      ) {
+        Ok(verifier) => keys.entry(kid).or_default().push(verifier),
+        Err(reason) => skipped.push(SkippedJwk {
+          kid: Some(kid),
+          reason,
+        }),
+      }
+    }
+
+    Ok(Self { keys, skipped })
+  }
+
+  #[napi]
+  pub fn verify(&self, env: Env, token: String) -> napi::Result<Claims> {
+    let claims = self
+      .verify_inner(&token)
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(claims)
+  }
+
+  #[napi(getter)]
+  pub fn key_ids(&self) -> Vec<String> {
+    self.keys.keys().cloned().collect()
+  }
+
+  #[napi(getter)]
+  /// Keys of the set that were skipped, with the reason for each
+  pub fn skipped_keys(&self) -> Vec<SkippedJwk> {
+    self.skipped.clone()
+  }
+}
@@ -0,0 +1,258 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use napi::bindgen_prelude::{Buffer, ClassInstance};
+use napi::{Either, Env};
+use napi_derive::napi;
+use serde_json::{Map, Value};
+
+use crate::errors::{Error, ErrorCode};
+use crate::header::Header;
+use crate::jwt_client::JwtClient;
+
+/// One signature of a JWS JSON serialization
+#[napi(object, object_to_js = false)]
+pub struct JwsSigner {
+  pub client: ClassInstance<JwtClient>,
+  /// Set over the client's own header, and covered by the signature
+  pub protected: Option<Header>,
+  /// Sent alongside the signature, but not covered by it
+  pub header: Option<Header>,
+}
+
+#[napi(object)]
+pub struct JwsSignature {
+  pub protected: String,
+  pub header: Option<Map<String, Value>>,
+  pub signature: String,
+}
+
+/// JWS in the general JSON serialization, with any number of signatures
+#[napi(object)]
+pub struct GeneralJws {
+  pub payload: String,
+  pub signatures: Vec<JwsSignature>,
+}
+
+/// JWS in the flattened JSON serialization, with a single signature
+#[napi(object)]
+pub struct FlattenedJws {
+  pub payload: String,
+  pub protected: String,
+  pub header: Option<Map<String, Value>>,
+  pub signature: String,
+}
+
+#[napi(object, object_from_js = false)]
+pub struct VerifiedJws {
+  pub payload: Buffer,
+  /// Protected and unprotected parameters of each verified signature, in the order of the verifiers
+  pub headers: Vec<Header>,
+}
+
+#[inline]
+fn malformed(msg: impl Into<String>) -> Error {
+  Error::validation(ErrorCode::Malformed, msg)
+}
+
+fn sign_one(encoded_payload: &str, signer: &JwsSigner) -> Result<JwsSignature, Error> {
+  let protected = signer.client.signer.payload_header(
+    signer
+      .protected
+      .as_ref()
+      .map(Header::to_params)
+      .unwrap_or_default(),
+  )?;
+  let header = signer.header.as_ref().map(Header::to_params);
+  if let Some(name) = header
+    .iter()
+    .flat_map(Map::keys)
+    .find(|name| protected.contains_key(*name))
+  {
+    return Err(malformed(format!(
+      "`{name}` is both a protected and an unprotected header parameter"
+    )));
+  }
+
+  let (protected, signature) = signer
+    .client
+    .signer
+    .sign_json(encoded_payload, &protected)?;
+
+  Ok(JwsSignature {
+    protected,
+    header,
+    signature,
+  })
+}
+
+/// Header of one signature, after checking that its protected and unprotected parameters are
+/// disjoint and that it has no critical extensions
+fn verify_one(
+  client: &JwtClient,
+  encoded_payload: &str,
+  protected: &str,
+  header: Option<&Map<String, Value>>,
+  signature: &str,
+) -> Result<jsonwebtoken::Header, Error> {
+  let mut params: Map<String, Value> = URL_SAFE_NO_PAD
+    .decode(protected)
+    .ok()
+    .and_then(|json| serde_json::from_slice(&json).ok())
+    .ok_or_else(|| malformed("Protected header is not base64url encoded JSON"))?;
+  if !params.contains_key("alg") {
+    return Err(malformed("Protected header has no `alg`"));
+  }
+  if params.contains_key("crit") || header.is_some_and(|header| header.contains_key("crit")) {
+    return Err(malformed("Critical header parameters are not supported"));
+  }
+
+  for (name, value) in header.into_iter().flatten() {
+    if params.insert(name.clone(), value.clone()).is_some() {
+      return Err(malformed(format!(
+        "`{name}` is both a protected and an unprotected header parameter"
+      )));
+    }
+  }
+  let header: jsonwebtoken::Header =
+    serde_json::from_value(Value::Object(params)).map_err(|e| malformed(e.to_string()))?;
+
+  let message = format!("{protected}.{encoded_payload}");
+  client
+    .verifier
+    .verify_signature(&header, message.as_bytes(), signature)?;
+
+  Ok(header)
+}
+
+#[inline]
+fn payload_bytes(payload: &Either<String, Buffer>) -> &[u8] {
+  match payload {
+    Either::A(s) => s.as_bytes(),
+    Either::B(buff) => buff,
+  }
+}
+
+#[inline]
+fn decode_payload(encoded_payload: &str) -> Result<Buffer, Error> {
+  URL_SAFE_NO_PAD
+    .decode(encoded_payload)
+    .map(Buffer::from)
+    .map_err(|_| malformed("Payload is not base64url encoded"))
+}
+
+fn verify_general_inner(
+  jws: &GeneralJws,
+  verifiers: &[ClassInstance<JwtClient>],
+) -> Result<VerifiedJws, Error> {
+  let mut headers = Vec::with_capacity(verifiers.len());
+  for client in verifiers {
+    let mut verified = None;
+    let mut error = None;
+    for signature in &jws.signatures {
+      match verify_one(
+        client,
+        &jws.payload,
+        &signature.protected,
+        signature.header.as_ref(),
+        &signature.signature,
+      ) {
+        Ok(header) => {
+          verified = Some(header);
+          break;
+        }
+        // Signatures by other keys are expected, so an algorithm mismatch is the least telling error
+        Err(e) if error.is_none() || e.code() != ErrorCode::InvalidAlgorithm => error = Some(e),
+        Err(_) => {}
+      }
+    }
+
+    let header = verified.ok_or_else(|| {
+      error
+        .unwrap_or_else(|| Error::validation(ErrorCode::InvalidSignature, "No signature to verify"))
+    })?;
+    headers.push(Header::try_from(&header)?);
+  }
+
+  Ok(VerifiedJws {
+    payload: decode_payload(&jws.payload)?,
+    headers,
+  })
+}
+
+fn verify_flattened_inner(jws: &FlattenedJws, verifier: &JwtClient) -> Result<VerifiedJws, Error> {
+  let header = verify_one(
+    verifier,
+    &jws.payload,
+    &jws.protected,
+    jws.header.as_ref(),
+    &jws.signature,
+  )?;
+
+  Ok(VerifiedJws {
+    payload: decode_payload(&jws.payload)?,
+    headers: vec![Header::try_from(&header)?],
+  })
+}
+
+#[napi]
+/// Sign `payload` once per signer, as a JWS in the general JSON serialization
+pub fn sign_general_jws(
+  payload: Either<String, Buffer>,
+  signers: Vec<JwsSigner>,
+) -> Result<GeneralJws, Error> {
+  let encoded_payload = URL_SAFE_NO_PAD.encode(payload_bytes(&payload));
+  let signatures = signers
+    .iter()
+    .map(|signer| sign_one(&encoded_payload, signer))
+    .collect::<Result<_, _>>()?;
+
+  Ok(GeneralJws {
+    payload: encoded_payload,
+    signatures,
+  })
+}
+
+#[napi]
+/// Sign `payload` as a JWS in the flattened JSON serialization
+pub fn sign_flattened_jws(
+  payload: Either<String, Buffer>,
+  signer: JwsSigner,
+) -> Result<FlattenedJws, Error> {
+  let encoded_payload = URL_SAFE_NO_PAD.encode(payload_bytes(&payload));
+  let JwsSignature {
+    protected,
+    header,
+    signature,
+  } = sign_one(&encoded_payload, &signer)?;
+
+  Ok(FlattenedJws {
+    payload: encoded_payload,
+    protected,
+    header,
+    signature,
+  })
+}
+
+#[napi]
+/// Verify a JWS in the general JSON serialization. Every verifier has to match one of the
+/// signatures. Only the signatures are checked, the payload is returned as is.
+pub fn verify_general_jws(
+  env: Env,
+  jws: GeneralJws,
+  verifiers: Vec<ClassInstance<JwtClient>>,
+) -> napi::Result<VerifiedJws> {
+  let verified = verify_general_inner(&jws, &verifiers).map_err(|e| e.into_js_error(env))?;
+  Ok(verified)
+}
+
+#[napi]
+/// Verify a JWS in the flattened JSON serialization. Only the signature is checked, the payload
+/// is returned as is.
+pub fn verify_flattened_jws(
+  env: Env,
+  jws: FlattenedJws,
+  verifier: ClassInstance<JwtClient>,
+) -> napi::Result<VerifiedJws> {
+  let verified = verify_flattened_inner(&jws, &verifier).map_err(|e| e.into_js_error(env))?;
+  Ok(verified)
+}
@@ -1,151 +1,204 @@
+use std::sync::Arc;
 use std::time::Duration;
 
-use jsonwebtoken::{DecodingKey, EncodingKey};
-use napi::bindgen_prelude::Buffer;
-use napi::Either;
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Either, Env, JsUnknown};
 use napi_derive::napi;
+use serde_json::{Map, Value};
 
-use crate::claims::{ClaimOpts, Claims};
-use crate::errors::Error;
+use crate::claims::{ClaimOpts, Claims, DefaultClaims, VerifiedClaims};
+use crate::clock::Clock;
+use crate::errors::{Error, ErrorCode, ErrorInfo};
 use crate::header::Header;
+use crate::keys::{get_asymmetric_keys, get_symmetric_keys};
+use crate::replay::{replay_guard, ReplayGuardOpt};
+use crate::revocation::Revocations;
+use crate::signer::{is_octet_stream, Signer, OCTET_STREAM};
+use crate::tasks::{SignTask, VerifyManyTask, VerifyTask};
 use crate::validation::Validation;
+use crate::verifier::{CacheKey, PreviousKey, TokenCache, Verifier};
 
-#[napi(object)]
+#[napi(object, object_to_js = false)]
 #[derive(Default)]
 pub struct JwtClientInitOpts {
   pub header: Option<Header>,
   pub validation: Option<Validation>,
+  /// Put custom claims beside `exp`, `iss` etc. instead of under a `data` claim, as most other
+  /// issuers do. Verified claims are then returned the same way, as a plain object rather than
+  /// `Claims`.
+  ///
+  /// Tokens still need an `exp` claim to verify, since `Claims` always has one.
+  ///
+  /// Defaults to `false`.
+  pub flat_claims: Option<bool>,
+  /// Claims added to every token signed with `sign`, `signAsync` and `signMany`
+  pub default_claims: Option<DefaultClaims>,
+  /// Accept each token only once, rejecting later verifications of the same `jti` until it expires.
+  /// Tokens without a `jti` are rejected.
+  ///
+  /// Either an `InMemoryReplayGuard`, or a function `(jti, expiresAt) => boolean` keeping the ids
+  /// elsewhere, which returns `false` if it has seen `jti` before and otherwise remembers it until
+  /// `expiresAt` (UTC timestamp, seconds from epoch time). Tokens are rejected if it throws.
+  pub replay_guard: Option<ReplayGuardOpt>,
+  /// Time used for `exp` and `iat` when signing, and for checking `exp`, `nbf` and `iat` when
+  /// verifying. Replays, revocations and the grace period of rotated keys are tracked against it
+  /// as well. Defaults to the system time.
+  pub clock: Option<Clock>,
 }
 
-#[napi]
-pub struct JwtClient {
-  encoding_key: jsonwebtoken::EncodingKey,
-  decoding_key: jsonwebtoken::DecodingKey,
-  header: jsonwebtoken::Header,
-  validation: jsonwebtoken::Validation,
+#[napi(object)]
+pub struct SignItem {
+  pub data: serde_json::Map<String, serde_json::Value>,
+  pub expires_in_seconds: u32,
+  pub claim_opts: Option<ClaimOpts>,
 }
 
-#[inline]
-fn get_encoding_key(key: &[u8], algorithm: jsonwebtoken::Algorithm) -> Result<EncodingKey, Error> {
-  use jsonwebtoken::Algorithm as Alg;
-
-  let enc_key_res = match algorithm {
-    // HMAC family
-    Alg::HS256 | Alg::HS384 | Alg::HS512 => Ok(EncodingKey::from_secret(key)),
-
-    // RSA family
-    Alg::RS256 | Alg::RS384 | Alg::RS512 | Alg::PS256 | Alg::PS384 | Alg::PS512 => {
-      EncodingKey::from_rsa_pem(key)
-    }
-
-    // EC family
-    Alg::ES256 | Alg::ES384 => EncodingKey::from_ec_pem(key),
-
-    // ED family
-    Alg::EdDSA => EncodingKey::from_ed_pem(key),
-  };
-
-  enc_key_res.map_err(Error::from)
+#[napi(object, object_from_js = false)]
+pub struct VerifiedToken {
+  pub header: Header,
+  pub claims: VerifiedClaims,
 }
 
-#[inline]
-fn get_decoding_key(key: &[u8], algorithm: jsonwebtoken::Algorithm) -> Result<DecodingKey, Error> {
-  use jsonwebtoken::Algorithm as Alg;
+impl VerifiedToken {
+  #[inline]
+  fn new(value: jsonwebtoken::TokenData<Claims>, flat_claims: bool) -> Result<Self, Error> {
+    Ok(Self {
+      header: Header::try_from(&value.header)?,
+      claims: value.claims.into_verified(flat_claims)?,
+    })
+  }
+}
 
-  let dec_key_res = match algorithm {
-    // HMAC family
-    Alg::HS256 | Alg::HS384 | Alg::HS512 => Ok(DecodingKey::from_secret(key)),
+#[napi(object, object_from_js = false)]
+pub struct VerifyResult {
+  /// Present if the token was verified successfully
+  pub claims: Option<VerifiedClaims>,
+  /// Present if verification failed
+  pub error: Option<ErrorInfo>,
+}
 
-    // RSA family
-    Alg::RS256 | Alg::RS384 | Alg::RS512 | Alg::PS256 | Alg::PS384 | Alg::PS512 => {
-      DecodingKey::from_rsa_pem(key)
+impl From<Result<VerifiedClaims, Error>> for VerifyResult {
+  #[inline]
+  fn from(value: Result<VerifiedClaims, Error>) -> Self {
+    match value {
+      Ok(claims) => Self {
+        claims: Some(claims),
+        error: None,
+      },
+      Err(e) => Self {
+        claims: None,
+        error: Some(e.into()),
+      },
     }
+  }
+}
 
-    // EC family
-    Alg::ES256 | Alg::ES384 => DecodingKey::from_ec_pem(key),
-
-    // ED family
-    Alg::EdDSA => DecodingKey::from_ed_pem(key),
-  };
-
-  dec_key_res.map_err(Error::from)
+#[napi]
+pub struct JwtClient {
+  pub(crate) signer: Arc<Signer>,
+  pub(crate) verifier: Arc<Verifier>,
 }
 
 #[inline]
-fn get_header_validation(
+fn get_signer_verifier(
   opts: Option<JwtClientInitOpts>,
-) -> (jsonwebtoken::Header, jsonwebtoken::Validation) {
+  get_keys: impl FnOnce(
+    jsonwebtoken::Algorithm,
+  ) -> Result<(jsonwebtoken::EncodingKey, jsonwebtoken::DecodingKey), Error>,
+) -> Result<(Signer, Verifier), Error> {
   let opts = opts.unwrap_or_default();
-  let header: jsonwebtoken::Header = opts.header.unwrap_or_default().into();
-  let validation = opts
-    .validation
-    .unwrap_or_default()
-    .for_jsonwebtoken(header.alg);
+  let header = jsonwebtoken::Header::try_from(opts.header.unwrap_or_default())?;
+  let (validation, claim_checks) = opts.validation.unwrap_or_default().into_parts(header.alg);
+  let flat_claims = opts.flat_claims.unwrap_or_default();
+  let (encoding_key, decoding_key) = get_keys(header.alg)?;
+
+  let signer = Signer {
+    key_family: header.alg.into(),
+    header,
+    encoding_key,
+    flat_claims,
+    default_claims: opts.default_claims.unwrap_or_default(),
+    clock: opts.clock.clone().unwrap_or_default(),
+  };
+  let verifier = Verifier {
+    decoding_key: Arc::new(decoding_key),
+    key_family: signer.key_family,
+    key_id: signer.header.kid.clone(),
+    previous_keys: Arc::new([]),
+    validation,
+    claim_checks,
+    flat_claims,
+    replay_guard: opts.replay_guard.map(replay_guard),
+    revocations: None,
+    cache_scope: None,
+    clock: opts.clock,
+  };
 
-  (header, validation)
+  Ok((signer, verifier))
 }
 
-#[inline]
-fn get_symmetric_keys(
-  secret_key: Either<String, Buffer>,
-  alg: jsonwebtoken::Algorithm,
-) -> Result<(jsonwebtoken::EncodingKey, jsonwebtoken::DecodingKey), Error> {
-  let (encoding_key, decoding_key) = match secret_key {
-    Either::A(s) => {
-      let sb = s.as_bytes();
-      let encoding_key = get_encoding_key(sb, alg)?;
-      let decoding_key = get_decoding_key(sb, alg)?;
-
-      (encoding_key, decoding_key)
-    }
-    Either::B(buff) => {
-      let encoding_key = get_encoding_key(&buff, alg)?;
-      let decoding_key = get_decoding_key(&buff, alg)?;
+#[napi(object)]
+pub struct RotateKeysOpts {
+  /// How long tokens signed with the previous keys are still accepted, in seconds
+  pub grace_period_secs: u32,
+  /// Public key matching the new signing key. Required unless the algorithm is symmetric.
+  pub public_key: Option<Either<String, Buffer>>,
+  /// `kid` header for tokens signed with the new key, used to pick the key when verifying
+  pub key_id: Option<String>,
+}
 
-      (encoding_key, decoding_key)
+fn rotate_keys(
+  signer: &Signer,
+  verifier: &Verifier,
+  new_signing_key: Either<String, Buffer>,
+  opts: RotateKeysOpts,
+) -> Result<(Signer, Verifier), Error> {
+  use jsonwebtoken::Algorithm as Alg;
+
+  let alg = signer.header.alg;
+  let (encoding_key, decoding_key) = match (alg, opts.public_key) {
+    (Alg::HS256 | Alg::HS384 | Alg::HS512, _) => get_symmetric_keys(new_signing_key, alg)?,
+    (_, Some(public_key)) => get_asymmetric_keys(alg, public_key, new_signing_key)?,
+    (_, None) => {
+      return Err(Error::InvalidKey(format!(
+        "`publicKey` is required to rotate {alg:?} keys"
+      )))
     }
   };
 
-  Ok((encoding_key, decoding_key))
-}
-
-#[inline]
-fn get_asymmetric_keys(
-  alg: jsonwebtoken::Algorithm,
-  pub_k: Either<String, Buffer>,
-  priv_k: Either<String, Buffer>,
-) -> Result<(jsonwebtoken::EncodingKey, jsonwebtoken::DecodingKey), Error> {
-  let encoding_key = match priv_k {
-    Either::A(s) => get_encoding_key(s.as_bytes(), alg),
-    Either::B(buff) => get_encoding_key(&buff, alg),
-  }?;
-
-  let decoding_key = match pub_k {
-    Either::A(s) => get_decoding_key(s.as_bytes(), alg),
-    Either::B(buff) => get_decoding_key(&buff, alg),
-  }?;
-
-  Ok((encoding_key, decoding_key))
-}
-
-#[inline]
-fn sign_claims(
-  header: &jsonwebtoken::Header,
-  enc_key: &jsonwebtoken::EncodingKey,
-  claims: &Claims,
-) -> Result<String, Error> {
-  jsonwebtoken::encode(header, &claims, enc_key).map_err(Error::from)
-}
+  let mut header = signer.header.clone();
+  header.kid = opts.key_id;
+  let new_signer = Signer {
+    header,
+    encoding_key,
+    key_family: alg.into(),
+    flat_claims: signer.flat_claims,
+    default_claims: signer.default_claims.clone(),
+    clock: signer.clock.clone(),
+  };
 
-#[inline]
-fn verify_and_decode(
-  token: &str,
-  dec_key: &jsonwebtoken::DecodingKey,
-  valid: &jsonwebtoken::Validation,
-) -> Result<Claims, Error> {
-  jsonwebtoken::decode::<Claims>(token, dec_key, valid)
-    .map(|c| c.claims)
-    .map_err(Error::from)
+  let now = verifier.now();
+  let replaced = PreviousKey {
+    key_id: verifier.key_id.clone(),
+    decoding_key: verifier.decoding_key.clone(),
+    valid_until: now + u64::from(opts.grace_period_secs),
+  };
+  let previous_keys = std::iter::once(replaced)
+    .chain(
+      verifier
+        .previous_keys
+        .iter()
+        .filter(|key| key.valid_until >= now)
+        .cloned(),
+    )
+    .collect();
+
+  let mut new_verifier = verifier.clone();
+  new_verifier.previous_keys = previous_keys;
+  new_verifier.decoding_key = Arc::new(decoding_key);
+  new_verifier.key_id.clone_from(&new_signer.header.kid);
+
+  Ok((new_signer, new_verifier))
 }
 
 #[napi]
@@ -156,14 +209,11 @@ impl JwtClient {
     secret_key: Either<String, Buffer>,
     opts: Option<JwtClientInitOpts>,
   ) -> Result<Self, Error> {
-    let (header, validation) = get_header_validation(opts);
-    let (encoding_key, decoding_key) = get_symmetric_keys(secret_key, header.alg)?;
+    let (signer, verifier) = get_signer_verifier(opts, |alg| get_symmetric_keys(secret_key, alg))?;
 
     Ok(Self {
-      header,
-      encoding_key,
-      decoding_key,
-      validation,
+      signer: Arc::new(signer),
+      verifier: Arc::new(verifier),
     })
   }
 
@@ -174,14 +224,12 @@ impl JwtClient {
     priv_key: Either<String, Buffer>,
     opts: Option<JwtClientInitOpts>,
   ) -> Result<Self, Error> {
-    let (header, validation) = get_header_validation(opts);
-    let (encoding_key, decoding_key) = get_asymmetric_keys(header.alg, pub_key, priv_key)?;
+    let (signer, verifier) =
+      get_signer_verifier(opts, |alg| get_asymmetric_keys(alg, pub_key, priv_key))?;
 
     Ok(Self {
-      header,
-      validation,
-      encoding_key,
-      decoding_key,
+      signer: Arc::new(signer),
+      verifier: Arc::new(verifier),
     })
   }
 
@@ -191,41 +239,252 @@ impl JwtClient {
     data: serde_json::Map<String, serde_json::Value>,
     expires_in_seconds: u32,
     claim_opts: Option<ClaimOpts>,
-  ) -> napi::Result<String> {
-    let claims = Claims::new(data, expires_in_seconds, claim_opts);
-    let tok = sign_claims(&self.header, &self.encoding_key, &claims)?;
+  ) -> Result<String, Error> {
+    let claims = self.signer.claims(data, expires_in_seconds, claim_opts);
+    let tok = self.signer.sign(&claims)?;
     Ok(tok)
   }
 
   #[napi]
-  pub fn sign_claims(&self, claims: &Claims) -> napi::Result<String> {
-    let tok = sign_claims(&self.header, &self.encoding_key, claims)?;
+  pub fn sign_async(
+    &self,
+    data: serde_json::Map<String, serde_json::Value>,
+    expires_in_seconds: u32,
+    claim_opts: Option<ClaimOpts>,
+  ) -> AsyncTask<SignTask> {
+    let claims = self.signer.claims(data, expires_in_seconds, claim_opts);
+    AsyncTask::new(SignTask {
+      signer: self.signer.clone(),
+      claims,
+      error: None,
+    })
+  }
+
+  #[napi]
+  pub fn sign_claims(&self, claims: &Claims) -> Result<String, Error> {
+    let tok = self.signer.sign(claims)?;
     Ok(tok)
   }
 
   #[napi]
-  pub fn verify(&self, token: String) -> napi::Result<Claims> {
-    let claims = verify_and_decode(&token, &self.decoding_key, &self.validation)?;
+  /// Sign a batch of payloads in one call, returning the tokens in the same order
+  pub fn sign_many(&self, items: Vec<SignItem>) -> Result<Vec<String>, Error> {
+    let claims = items.into_iter().map(|item| {
+      self
+        .signer
+        .claims(item.data, item.expires_in_seconds, item.claim_opts)
+    });
+    let toks = self.signer.sign_many(claims)?;
+    Ok(toks)
+  }
+
+  #[napi]
+  /// `validation` is layered over the client's own validation for this call only
+  pub fn verify(
+    &self,
+    env: Env,
+    token: String,
+    validation: Option<Validation>,
+  ) -> napi::Result<VerifiedClaims> {
+    let verifier = self.verifier.layered(validation);
+    let claims = verifier
+      .verify(&token)
+      .and_then(|token_data| {
+        verifier.check_replay(env, &token_data.claims)?;
+        token_data.claims.into_verified(verifier.flat_claims)
+      })
+      .map_err(|e| e.into_js_error(env))?;
     Ok(claims)
   }
 
+  #[napi]
+  /// Same as `verify`, off the main thread, with `validation` layered over the client's own
+  /// validation for this call only
+  pub fn verify_async(
+    &self,
+    token: String,
+    validation: Option<Validation>,
+  ) -> AsyncTask<VerifyTask> {
+    AsyncTask::new(VerifyTask {
+      token,
+      verifier: self.verifier.layered(validation),
+      cache: None,
+      error: None,
+    })
+  }
+
+  #[napi]
+  /// `validation` is layered over the client's own validation for this call only
+  pub fn verify_complete(
+    &self,
+    env: Env,
+    token: String,
+    validation: Option<Validation>,
+  ) -> napi::Result<VerifiedToken> {
+    let verifier = self.verifier.layered(validation);
+    let token_data = verifier
+      .verify(&token)
+      .and_then(|token_data| {
+        verifier.check_replay(env, &token_data.claims)?;
+        VerifiedToken::new(token_data, verifier.flat_claims)
+      })
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(token_data)
+  }
+
+  #[napi]
+  /// Verify every token, reporting failures per token instead of throwing
+  pub fn verify_many(&self, env: Env, tokens: Vec<String>) -> Vec<VerifyResult> {
+    let mut results = self.verifier.verify_many(tokens, None);
+    self.verifier.check_replays(env, &mut results);
+    results
+  }
+
+  #[napi]
+  /// Same as `verifyMany`, with the tokens split across a pool of threads off the main thread
+  pub fn verify_many_async(&self, tokens: Vec<String>) -> AsyncTask<VerifyManyTask> {
+    AsyncTask::new(VerifyManyTask {
+      tokens,
+      verifier: self.verifier.clone(),
+      cache: None,
+      error: None,
+    })
+  }
+
+  #[napi]
+  /// Sign `payload` as is, without base64url encoding it (RFC 7797). The payload is left out of the
+  /// returned JWS (`header..signature`) and has to be sent alongside it.
+  pub fn sign_detached(&self, payload: Buffer) -> Result<String, Error> {
+    self.signer.sign_detached(&payload)
+  }
+
+  #[napi]
+  /// Verify a JWS with a detached payload against `payload`, returning its header
+  pub fn verify_detached(&self, env: Env, jws: String, payload: Buffer) -> napi::Result<Header> {
+    let header = self
+      .verifier
+      .verify_detached(&jws, &payload)
+      .and_then(|header| Header::try_from(&header))
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(header)
+  }
+
+  #[napi]
+  /// Sign any JSON value, or raw bytes, as a compact JWS. Unlike `sign`, nothing is added to the
+  /// payload, so it has no `exp` unless it carries one itself. Raw bytes are marked with a `cty`
+  /// header of `application/octet-stream`.
+  pub fn sign_payload(
+    &self,
+    env: Env,
+    payload: Either<Buffer, JsUnknown>,
+  ) -> Result<String, Error> {
+    match payload {
+      Either::A(buff) => self.signer.sign_payload(&buff, Some(OCTET_STREAM)),
+      Either::B(value) => {
+        let value: Value = env
+          .from_js_value(value)
+          .map_err(|e| Error::Generic(e.reason))?;
+        let json = serde_json::to_vec(&value).map_err(|e| Error::Generic(e.to_string()))?;
+        self.signer.sign_payload(&json, None)
+      }
+    }
+  }
+
+  #[napi]
+  /// Verify a token from `signPayload`, with `validation` layered over the client's validation for
+  /// this call only. Registered claims are validated only if the payload has them with their
+  /// registered type, so `exp` is not required. Returns the payload as a Buffer if the token has a
+  /// `cty` header of `application/octet-stream`, and as JSON otherwise. With a replay guard, the
+  /// payload must be a JSON object with a `jti`, as with `verify`, and is accepted only once.
+  pub fn verify_payload(
+    &self,
+    env: Env,
+    token: String,
+    validation: Option<Validation>,
+  ) -> napi::Result<Either<Value, Buffer>> {
+    let verifier = self.verifier.layered(validation);
+    let verified = verifier
+      .verify_payload(&token)
+      .and_then(|(header, payload)| {
+        if is_octet_stream(header.cty.as_deref()) {
+          return Ok(Either::B(payload.into()));
+        }
+        serde_json::from_slice(&payload)
+          .map(Either::A)
+          .map_err(|_| {
+            Error::validation(
+              ErrorCode::Malformed,
+              "Payload is not JSON, and the token has no `cty` of `application/octet-stream`",
+            )
+          })
+      })
+      .and_then(|verified| {
+        match &verified {
+          Either::A(Value::Object(claims)) => verifier.check_replay(env, claims)?,
+          _ => verifier.check_replay(env, &Map::new())?,
+        }
+        Ok(verified)
+      })
+      .map_err(|e| e.into_js_error(env))?;
+
+    Ok(verified)
+  }
+
+  #[napi]
+  /// Sign with a new key from now on, while still accepting tokens signed with the previous keys
+  /// for `gracePeriodSecs`
+  pub fn rotate_keys(
+    &mut self,
+    new_signing_key: Either<String, Buffer>,
+    opts: RotateKeysOpts,
+  ) -> Result<(), Error> {
+    let (signer, verifier) = rotate_keys(&self.signer, &self.verifier, new_signing_key, opts)?;
+    self.signer = Arc::new(signer);
+    self.verifier = Arc::new(verifier);
+    Ok(())
+  }
+
   #[napi(getter)]
-  pub fn header(&self) -> Header {
-    (&self.header).into()
+  pub fn header(&self) -> Result<Header, Error> {
+    Header::try_from(&self.signer.header)
   }
 }
 
 #[napi]
 pub struct JwtCacheClient {
-  encoding_key: jsonwebtoken::EncodingKey,
-  decoding_key: jsonwebtoken::DecodingKey,
-  header: jsonwebtoken::Header,
-  validation: jsonwebtoken::Validation,
-  cache: mini_moka::unsync::Cache<String, Claims>,
+  signer: Arc<Signer>,
+  verifier: Arc<Verifier>,
+  cache: TokenCache,
+  revocations: Arc<Revocations>,
   ttl_secs: u32,
   max_capacity: u32,
 }
 
+impl JwtCacheClient {
+  /// Revocations of ids and subjects are kept until `keep_until` (plus leeway), or until they are
+  /// lifted, since the tokens they cover may have been signed elsewhere with a longer lifetime
+  fn revocation_drop_after(&self, keep_until: Option<i64>) -> u64 {
+    keep_until.map_or(u64::MAX, |keep_until| {
+      u64::try_from(keep_until)
+        .unwrap_or_default()
+        .saturating_add(self.verifier.validation.leeway)
+    })
+  }
+
+  fn evict_where(&self, pred: impl Fn(&Claims) -> bool) {
+    let keys: Vec<CacheKey> = self
+      .cache
+      .iter()
+      .filter(|entry| pred(&entry.value().token_data.claims))
+      .map(|entry| entry.key().clone())
+      .collect();
+
+    for key in keys {
+      self.cache.invalidate(&key);
+    }
+  }
+}
+
 #[napi]
 impl JwtCacheClient {
   #[napi(constructor)]
@@ -235,20 +494,21 @@ impl JwtCacheClient {
     max_capacity: u32,
     opts: Option<JwtClientInitOpts>,
   ) -> Result<Self, Error> {
-    let cache = mini_moka::unsync::Cache::builder()
+    let cache = mini_moka::sync::Cache::builder()
       .max_capacity(u64::from(max_capacity))
       .time_to_live(Duration::from_secs(u64::from(ttl_secs)))
       .build();
 
-    let (header, validation) = get_header_validation(opts);
-    let (encoding_key, decoding_key) = get_symmetric_keys(secret_key, header.alg)?;
+    let (signer, mut verifier) =
+      get_signer_verifier(opts, |alg| get_symmetric_keys(secret_key, alg))?;
+    let revocations = Arc::new(Revocations::default());
+    verifier.revocations = Some(revocations.clone());
 
     Ok(Self {
-      header,
-      validation,
-      encoding_key,
-      decoding_key,
+      signer: Arc::new(signer),
+      verifier: Arc::new(verifier),
       cache,
+      revocations,
       ttl_secs,
       max_capacity,
     })
@@ -262,20 +522,21 @@ impl JwtCacheClient {
     max_capacity: u32,
     opts: Option<JwtClientInitOpts>,
   ) -> Result<Self, Error> {
-    let cache = mini_moka::unsync::Cache::builder()
+    let cache = mini_moka::sync::Cache::builder()
       .max_capacity(u64::from(max_capacity))
       .time_to_live(Duration::from_secs(u64::from(ttl_secs)))
       .build();
 
-    let (header, validation) = get_header_validation(opts);
-    let (encoding_key, decoding_key) = get_asymmetric_keys(header.alg, pub_key, priv_key)?;
+    let (signer, mut verifier) =
+      get_signer_verifier(opts, |alg| get_asymmetric_keys(alg, pub_key, priv_key))?;
+    let revocations = Arc::new(Revocations::default());
+    verifier.revocations = Some(revocations.clone());
 
     Ok(Self {
-      header,
-      validation,
-      encoding_key,
-      decoding_key,
+      signer: Arc::new(signer),
+      verifier: Arc::new(verifier),
       cache,
+      revocations,
       ttl_secs,
       max_capacity,
     })
@@ -286,34 +547,181 @@ impl JwtCacheClient {
     &self,
     data: serde_json::Map<String, serde_json::Value>,
     claim_opts: Option<ClaimOpts>,
-  ) -> napi::Result<String> {
-    let claims = Claims::new(data, self.ttl_secs, claim_opts);
-    let tok = sign_claims(&self.header, &self.encoding_key, &claims)?;
+  ) -> Result<String, Error> {
+    let claims = self.signer.claims(data, self.ttl_secs, claim_opts);
+    let tok = self.signer.sign(&claims)?;
     Ok(tok)
   }
 
   #[napi]
-  pub fn verify(&mut self, token: String) -> napi::Result<Claims> {
-    let claims = match self.cache.get(&token) {
-      Some(c) => c.to_owned(),
-      None => {
-        let claims = verify_and_decode(&token, &self.decoding_key, &self.validation)?;
-        self.cache.insert(token, claims.clone());
-        claims
-      }
-    };
+  pub fn sign_async(
+    &self,
+    data: serde_json::Map<String, serde_json::Value>,
+    claim_opts: Option<ClaimOpts>,
+  ) -> AsyncTask<SignTask> {
+    let claims = self.signer.claims(data, self.ttl_secs, claim_opts);
+    AsyncTask::new(SignTask {
+      signer: self.signer.clone(),
+      claims,
+      error: None,
+    })
+  }
 
+  #[napi]
+  /// `validation` is layered over the client's own validation for this call only
+  pub fn verify(
+    &self,
+    env: Env,
+    token: String,
+    validation: Option<Validation>,
+  ) -> napi::Result<VerifiedClaims> {
+    let verifier = self.verifier.layered(validation);
+    let claims = verifier
+      .verify_cached(&self.cache, token)
+      .and_then(|token_data| {
+        verifier.check_replay(env, &token_data.claims)?;
+        token_data.claims.into_verified(verifier.flat_claims)
+      })
+      .map_err(|e| e.into_js_error(env))?;
     Ok(claims)
   }
 
   #[napi]
-  pub fn invalidate_cache(&mut self) {
+  /// Same as `verify`, off the main thread, with `validation` layered over the client's own
+  /// validation for this call only. Results are cached apart for each `validation`.
+  pub fn verify_async(
+    &self,
+    token: String,
+    validation: Option<Validation>,
+  ) -> AsyncTask<VerifyTask> {
+    AsyncTask::new(VerifyTask {
+      token,
+      verifier: self.verifier.layered(validation),
+      cache: Some(self.cache.clone()),
+      error: None,
+    })
+  }
+
+  #[napi]
+  /// `validation` is layered over the client's own validation for this call only
+  pub fn verify_complete(
+    &self,
+    env: Env,
+    token: String,
+    validation: Option<Validation>,
+  ) -> napi::Result<VerifiedToken> {
+    let verifier = self.verifier.layered(validation);
+    let token_data = verifier
+      .verify_cached(&self.cache, token)
+      .and_then(|token_data| {
+        verifier.check_replay(env, &token_data.claims)?;
+        VerifiedToken::new(token_data, verifier.flat_claims)
+      })
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(token_data)
+  }
+
+  #[napi]
+  /// Verify every token, reporting failures per token instead of throwing
+  pub fn verify_many(&self, env: Env, tokens: Vec<String>) -> Vec<VerifyResult> {
+    let mut results = self.verifier.verify_many(tokens, Some(&self.cache));
+    self.verifier.check_replays(env, &mut results);
+    results
+  }
+
+  #[napi]
+  /// Same as `verifyMany`, with the tokens split across a pool of threads off the main thread
+  pub fn verify_many_async(&self, tokens: Vec<String>) -> AsyncTask<VerifyManyTask> {
+    AsyncTask::new(VerifyManyTask {
+      tokens,
+      verifier: self.verifier.clone(),
+      cache: Some(self.cache.clone()),
+      error: None,
+    })
+  }
+
+  #[napi]
+  pub fn invalidate_cache(&self) {
+    self.cache.invalidate_all();
+  }
+
+  #[napi]
+  /// Sign with a new key from now on, while still accepting tokens signed with the previous keys
+  /// for `gracePeriodSecs`. The token cache is cleared, so that tokens verified with the previous
+  /// keys are checked against them again, and fail once the grace period is over.
+  pub fn rotate_keys(
+    &mut self,
+    new_signing_key: Either<String, Buffer>,
+    opts: RotateKeysOpts,
+  ) -> Result<(), Error> {
+    let (signer, verifier) = rotate_keys(&self.signer, &self.verifier, new_signing_key, opts)?;
+    self.signer = Arc::new(signer);
+    self.verifier = Arc::new(verifier);
     self.cache.invalidate_all();
+    Ok(())
+  }
+
+  #[napi]
+  /// Reject this exact token from now on, until it expires. Its signature is checked first, so
+  /// that arbitrary tokens cannot be stored, but not its other claims, which a per-call validation
+  /// may accept. Expired tokens are left alone, they are rejected anyway.
+  pub fn revoke(&self, token: String) -> Result<(), Error> {
+    let exp = match self.verifier.verify_authentic(&token) {
+      Ok(token_data) => token_data.claims.exp_secs(),
+      Err(e) if e.code() == ErrorCode::Expired => return Ok(()),
+      Err(e) => return Err(e),
+    };
+
+    self.cache.invalidate(&CacheKey::unscoped(token.clone()));
+    self.revocations.revoke_token(
+      token,
+      exp.saturating_add(self.verifier.validation.leeway),
+      self.verifier.now(),
+    );
+    Ok(())
+  }
+
+  #[napi]
+  /// Reject every token with this `jti` from now on, until `keepUntil` (UTC timestamp, seconds
+  /// from epoch time) if given, e.g. the latest `exp` of those tokens, or until `unrevokeJti`
+  pub fn revoke_jti(&self, jti: String, keep_until: Option<i64>) {
+    self.evict_where(|claims| claims.jti.as_ref() == Some(&jti));
+
+    let drop_after = self.revocation_drop_after(keep_until);
+    self
+      .revocations
+      .revoke_jti(jti, drop_after, self.verifier.now());
+  }
+
+  #[napi]
+  /// Reject every token for this `sub` issued before `issuedBefore` (UTC timestamp, seconds from
+  /// epoch time), e.g. after a password change. Tokens without `iat` are rejected as well. Kept
+  /// until `keepUntil` if given, or until `unrevokeSubject`.
+  pub fn revoke_subject(&self, sub: String, issued_before: i64, keep_until: Option<i64>) {
+    let issued_before = u64::try_from(issued_before).unwrap_or_default();
+    self.evict_where(|claims| claims.sub.as_ref() == Some(&sub));
+
+    let drop_after = self.revocation_drop_after(keep_until);
+    self
+      .revocations
+      .revoke_subject(sub, issued_before, drop_after, self.verifier.now());
+  }
+
+  #[napi]
+  /// Accept tokens with this `jti` again
+  pub fn unrevoke_jti(&self, jti: String) {
+    self.revocations.unrevoke_jti(&jti);
+  }
+
+  #[napi]
+  /// Accept tokens for this `sub` again, whenever they were issued
+  pub fn unrevoke_subject(&self, sub: String) {
+    self.revocations.unrevoke_subject(&sub);
   }
 
   #[napi(getter)]
-  pub fn header(&self) -> Header {
-    (&self.header).into()
+  pub fn header(&self) -> Result<Header, Error> {
+    Header::try_from(&self.signer.header)
   }
 
   #[napi(getter)]
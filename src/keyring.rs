@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::Buffer;
+use napi::{Either, Env};
+use napi_derive::napi;
+
+use crate::algorithm::Algorithm;
+use crate::claims::VerifiedClaims;
+use crate::decode::decode_payload_unverified;
+use crate::errors::{Error, ErrorCode};
+use crate::keys::get_decoding_key;
+use crate::validation::{ClaimChecks, Validation};
+use crate::verifier::Verifier;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct JwtKeyringInitOpts {
+  /// Used for keys without an issuer, or whose issuer has no validation of its own
+  pub validation: Option<Validation>,
+  /// Expect custom claims beside `exp`, `iss` etc. instead of under a `data` claim, and return
+  /// them the same way, as a plain object rather than `Claims`. Tokens still need an `exp` claim
+  /// to verify, since `Claims` always has one.
+  ///
+  /// Defaults to `false`.
+  pub flat_claims: Option<bool>,
+}
+
+struct KeyringEntry {
+  kid: String,
+  algorithm: jsonwebtoken::Algorithm,
+  issuer: Option<String>,
+  verifier: Verifier,
+}
+
+/// Verifier for tokens from several issuers or keys, selecting the key by the token's `kid` header,
+/// or by its `iss` claim if it has no `kid`
+#[napi]
+pub struct JwtKeyring {
+  /// In the order they were added, which is the order keys of the same issuer are tried in
+  entries: Vec<KeyringEntry>,
+  default_validation: (jsonwebtoken::Validation, ClaimChecks),
+  issuer_validations: HashMap<String, (jsonwebtoken::Validation, ClaimChecks)>,
+  flat_claims: bool,
+}
+
+/// Validation for a key, pinned to the key's algorithm and, if it has one, its issuer
+fn key_validation(
+  default_validation: &(jsonwebtoken::Validation, ClaimChecks),
+  issuer_validations: &HashMap<String, (jsonwebtoken::Validation, ClaimChecks)>,
+  algorithm: jsonwebtoken::Algorithm,
+  issuer: Option<&str>,
+) -> (jsonwebtoken::Validation, ClaimChecks) {
+  let (mut validation, claim_checks) = issuer
+    .and_then(|iss| issuer_validations.get(iss))
+    .unwrap_or(default_validation)
+    .clone();
+  validation.algorithms = vec![algorithm];
+
+  if let (Some(iss), None) = (issuer, &validation.iss) {
+    validation.set_issuer(&[iss]);
+  }
+
+  (validation, claim_checks)
+}
+
+impl JwtKeyring {
+  fn verify_inner(&self, token: &str) -> Result<VerifiedClaims, Error> {
+    let header = jsonwebtoken::decode_header(token)?;
+
+    if let Some(kid) = header.kid {
+      let entry = self
+        .entries
+        .iter()
+        .find(|entry| entry.kid == kid)
+        .ok_or(Error::UnknownKeyId(kid))?;
+
+      let claims = entry.verifier.verify(token)?.claims;
+      return claims.into_verified(self.flat_claims);
+    }
+
+    let payload = decode_payload_unverified(token)?.claims;
+    let iss = payload
+      .get("iss")
+      .and_then(serde_json::Value::as_str)
+      .ok_or_else(|| {
+        Error::validation(
+          ErrorCode::NoMatchingKey,
+          "Token has neither a `kid` header nor an `iss` claim",
+        )
+      })?;
+
+    let mut result = Err(Error::validation(
+      ErrorCode::NoMatchingKey,
+      format!("No key found for issuer `{iss}`"),
+    ));
+    for entry in self
+      .entries
+      .iter()
+      .filter(|entry| entry.issuer.as_deref() == Some(iss))
+    {
+      result = entry
+        .verifier
+        .verify(token)
+        .and_then(|td| td.claims.into_verified(self.flat_claims));
+      if !matches!(&result, Err(e) if e.code() == ErrorCode::InvalidSignature) {
+        break;
+      }
+    }
+
+    result
+  }
+}
+
+#[napi]
+impl JwtKeyring {
+  #[napi(constructor)]
+  pub fn new(opts: Option<JwtKeyringInitOpts>) -> Self {
+    let opts = opts.unwrap_or_default();
+
+    Self {
+      entries: vec![],
+      default_validation: opts
+        .validation
+        .unwrap_or_default()
+        .into_parts(jsonwebtoken::Algorithm::default()),
+      issuer_validations: HashMap::new(),
+      flat_claims: opts.flat_claims.unwrap_or_default(),
+    }
+  }
+
+  #[napi]
+  /// Add a verification key, replacing any key with the same `kid`. Tokens without a `kid` are
+  /// matched to the key by `issuer`.
+  pub fn add_key(
+    &mut self,
+    kid: String,
+    algorithm: Algorithm,
+    key: Either<String, Buffer>,
+    issuer: Option<String>,
+  ) -> Result<(), Error> {
+    let algorithm: jsonwebtoken::Algorithm = algorithm.into();
+    let decoding_key = match key {
+      Either::A(s) => get_decoding_key(s.as_bytes(), algorithm),
+      Either::B(buff) => get_decoding_key(&buff, algorithm),
+    }?;
+
+    let (validation, claim_checks) = key_validation(
+      &self.default_validation,
+      &self.issuer_validations,
+      algorithm,
+      issuer.as_deref(),
+    );
+    let entry = KeyringEntry {
+      verifier: Verifier {
+        decoding_key: Arc::new(decoding_key),
+        key_family: algorithm.into(),
+        key_id: Some(kid.clone()),
+        previous_keys: Arc::new([]),
+        validation,
+        claim_checks,
+        flat_claims: self.flat_claims,
+        replay_guard: None,
+        revocations: None,
+        cache_scope: None,
+        clock: None,
+      },
+      kid,
+      algorithm,
+      issuer,
+    };
+
+    match self.entries.iter_mut().find(|e| e.kid == entry.kid) {
+      Some(existing) => *existing = entry,
+      None => self.entries.push(entry),
+    }
+
+    Ok(())
+  }
+
+  #[napi]
+  pub fn remove_key(&mut self, kid: String) {
+    self.entries.retain(|entry| entry.kid != kid);
+  }
+
+  #[napi]
+  /// Validation for the keys of `issuer`, used instead of the keyring's default validation.
+  /// Unless it sets `iss` itself, the token's `iss` must match `issuer`.
+  pub fn set_issuer_validation(&mut self, issuer: String, validation: Validation) {
+    let validation = validation.into_parts(jsonwebtoken::Algorithm::default());
+    self.issuer_validations.insert(issuer.clone(), validation);
+
+    for entry in &mut self.entries {
+      if entry.issuer.as_ref() == Some(&issuer) {
+        (entry.verifier.validation, entry.verifier.claim_checks) = key_validation(
+          &self.default_validation,
+          &self.issuer_validations,
+          entry.algorithm,
+          Some(&issuer),
+        );
+      }
+    }
+  }
+
+  #[napi]
+  pub fn verify(&self, env: Env, token: String) -> napi::Result<VerifiedClaims> {
+    let claims = self
+      .verify_inner(&token)
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(claims)
+  }
+
+  #[napi(getter)]
+  pub fn key_ids(&self) -> Vec<String> {
+    self.entries.iter().map(|entry| entry.kid.clone()).collect()
+  }
+}
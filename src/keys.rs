@@ -0,0 +1,186 @@
+use std::str::FromStr;
+
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, PublicKeyUse};
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use napi::bindgen_prelude::Buffer;
+use napi::Either;
+
+use crate::errors::Error;
+
+/// Kind of key an algorithm works with. `jsonwebtoken` keeps the family of its keys private, so
+/// it is tracked next to them wherever signatures are made or checked without `encode`/`decode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum KeyFamily {
+  Hmac,
+  Rsa,
+  Ec,
+  Ed,
+}
+
+impl From<jsonwebtoken::Algorithm> for KeyFamily {
+  #[inline]
+  fn from(value: jsonwebtoken::Algorithm) -> Self {
+    use jsonwebtoken::Algorithm as Alg;
+
+    match value {
+      Alg::HS256 | Alg::HS384 | Alg::HS512 => Self::Hmac,
+      Alg::RS256 | Alg::RS384 | Alg::RS512 | Alg::PS256 | Alg::PS384 | Alg::PS512 => Self::Rsa,
+      Alg::ES256 | Alg::ES384 => Self::Ec,
+      Alg::EdDSA => Self::Ed,
+    }
+  }
+}
+
+impl From<&AlgorithmParameters> for KeyFamily {
+  #[inline]
+  fn from(value: &AlgorithmParameters) -> Self {
+    match value {
+      AlgorithmParameters::OctetKey(_) => Self::Hmac,
+      AlgorithmParameters::RSA(_) => Self::Rsa,
+      AlgorithmParameters::EllipticCurve(_) => Self::Ec,
+      AlgorithmParameters::OctetKeyPair(_) => Self::Ed,
+    }
+  }
+}
+
+#[inline]
+pub(crate) fn get_encoding_key(
+  key: &[u8],
+  algorithm: jsonwebtoken::Algorithm,
+) -> Result<EncodingKey, Error> {
+  use jsonwebtoken::Algorithm as Alg;
+
+  let enc_key_res = match algorithm {
+    // HMAC family
+    Alg::HS256 | Alg::HS384 | Alg::HS512 => Ok(EncodingKey::from_secret(key)),
+
+    // RSA family
+    Alg::RS256 | Alg::RS384 | Alg::RS512 | Alg::PS256 | Alg::PS384 | Alg::PS512 => {
+      EncodingKey::from_rsa_pem(key)
+    }
+
+    // EC family
+    Alg::ES256 | Alg::ES384 => EncodingKey::from_ec_pem(key),
+
+    // ED family
+    Alg::EdDSA => EncodingKey::from_ed_pem(key),
+  };
+
+  enc_key_res.map_err(Error::from)
+}
+
+#[inline]
+pub(crate) fn get_decoding_key(
+  key: &[u8],
+  algorithm: jsonwebtoken::Algorithm,
+) -> Result<DecodingKey, Error> {
+  use jsonwebtoken::Algorithm as Alg;
+
+  let dec_key_res = match algorithm {
+    // HMAC family
+    Alg::HS256 | Alg::HS384 | Alg::HS512 => Ok(DecodingKey::from_secret(key)),
+
+    // RSA family
+    Alg::RS256 | Alg::RS384 | Alg::RS512 | Alg::PS256 | Alg::PS384 | Alg::PS512 => {
+      DecodingKey::from_rsa_pem(key)
+    }
+
+    // EC family
+    Alg::ES256 | Alg::ES384 => DecodingKey::from_ec_pem(key),
+
+    // ED family
+    Alg::EdDSA => DecodingKey::from_ed_pem(key),
+  };
+
+  dec_key_res.map_err(Error::from)
+}
+
+#[inline]
+pub(crate) fn get_symmetric_keys(
+  secret_key: Either<String, Buffer>,
+  alg: jsonwebtoken::Algorithm,
+) -> Result<(jsonwebtoken::EncodingKey, jsonwebtoken::DecodingKey), Error> {
+  let (encoding_key, decoding_key) = match secret_key {
+    Either::A(s) => {
+      let sb = s.as_bytes();
+      let encoding_key = get_encoding_key(sb, alg)?;
+      let decoding_key = get_decoding_key(sb, alg)?;
+
+      (encoding_key, decoding_key)
+    }
+    Either::B(buff) => {
+      let encoding_key = get_encoding_key(&buff, alg)?;
+      let decoding_key = get_decoding_key(&buff, alg)?;
+
+      (encoding_key, decoding_key)
+    }
+  };
+
+  Ok((encoding_key, decoding_key))
+}
+
+#[inline]
+pub(crate) fn get_asymmetric_keys(
+  alg: jsonwebtoken::Algorithm,
+  pub_k: Either<String, Buffer>,
+  priv_k: Either<String, Buffer>,
+) -> Result<(jsonwebtoken::EncodingKey, jsonwebtoken::DecodingKey), Error> {
+  let encoding_key = match priv_k {
+    Either::A(s) => get_encoding_key(s.as_bytes(), alg),
+    Either::B(buff) => get_encoding_key(&buff, alg),
+  }?;
+
+  let decoding_key = match pub_k {
+    Either::A(s) => get_decoding_key(s.as_bytes(), alg),
+    Either::B(buff) => get_decoding_key(&buff, alg),
+  }?;
+
+  Ok((encoding_key, decoding_key))
+}
+
+/// Signature algorithms a JWK may be used with.
+///
+/// The `alg` member wins if present, otherwise every algorithm of the key's family (narrowed by
+/// curve for EC keys) is allowed. Encryption-only keys, and an `alg` of another family than the
+/// key, yield an empty list.
+pub(crate) fn get_jwk_algorithms(jwk: &Jwk) -> Vec<jsonwebtoken::Algorithm> {
+  use jsonwebtoken::Algorithm as Alg;
+
+  if let Some(PublicKeyUse::Encryption) = jwk.common.public_key_use {
+    return vec![];
+  }
+
+  if let Some(key_alg) = jwk.common.key_algorithm {
+    return Alg::from_str(&key_alg.to_string())
+      .ok()
+      .filter(|alg| KeyFamily::from(*alg) == KeyFamily::from(&jwk.algorithm))
+      .map(|alg| vec![alg])
+      .unwrap_or_default();
+  }
+
+  match &jwk.algorithm {
+    AlgorithmParameters::OctetKey(_) => vec![Alg::HS256, Alg::HS384, Alg::HS512],
+    AlgorithmParameters::RSA(_) => vec![
+      Alg::RS256,
+      Alg::RS384,
+      Alg::RS512,
+      Alg::PS256,
+      Alg::PS384,
+      Alg::PS512,
+    ],
+    AlgorithmParameters::EllipticCurve(params) => match params.curve {
+      EllipticCurve::P256 => vec![Alg::ES256],
+      EllipticCurve::P384 => vec![Alg::ES384],
+      _ => vec![],
+    },
+    AlgorithmParameters::OctetKeyPair(params) => match params.curve {
+      EllipticCurve::Ed25519 => vec![Alg::EdDSA],
+      _ => vec![],
+    },
+  }
+}
+
+#[inline]
+pub(crate) fn get_jwk_decoding_key(jwk: &Jwk) -> Result<DecodingKey, Error> {
+  DecodingKey::from_jwk(jwk).map_err(Error::from)
+}
@@ -5,11 +5,31 @@
 
 mod algorithm;
 mod claims;
+mod clock;
+mod decode;
 mod errors;
 mod header;
+mod jwe;
+mod jwe_client;
+mod jwks_client;
+mod jws_json;
 mod jwt_client;
+mod keyring;
+mod keys;
+mod replay;
+mod revocation;
+mod signer;
+mod tasks;
 mod validation;
+mod verifier;
 
 pub use algorithm::Algorithm;
 pub use claims::{ClaimOpts, Claims};
+pub use decode::{decode_header, decode_unverified};
+pub use jwe_client::JweClient;
+pub use jwks_client::JwksClient;
+pub use jws_json::{
+  sign_flattened_jws, sign_general_jws, verify_flattened_jws, verify_general_jws,
+};
 pub use jwt_client::JwtClient;
+pub use keyring::JwtKeyring;
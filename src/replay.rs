@@ -0,0 +1,147 @@
+use std::collections::{BTreeSet, HashMap};
+use std::mem::ManuallyDrop;
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+
+use napi::bindgen_prelude::{ClassInstance, FunctionRef};
+use napi::{Either, Env};
+use napi_derive::napi;
+
+use crate::errors::Error;
+
+/// Remembers the `jti` of verified tokens so that each token is only accepted once
+pub(crate) trait ReplayGuard: Send + Sync {
+  /// Returns `false` if `jti` was already seen, otherwise remembers it until `expires_at`. Guards
+  /// may call into JS, so this is only ever called on the JS thread.
+  fn check_and_remember(
+    &self,
+    env: Env,
+    jti: &str,
+    expires_at: u64,
+    now: u64,
+  ) -> Result<bool, Error>;
+}
+
+/// `replayGuard` option of the clients, either the built-in guard or a JS function
+pub(crate) type ReplayGuardOpt =
+  Either<ClassInstance<InMemoryReplayGuard>, FunctionRef<(String, f64), bool>>;
+
+pub(crate) fn replay_guard(opt: ReplayGuardOpt) -> Arc<dyn ReplayGuard> {
+  match opt {
+    Either::A(in_memory) => in_memory.store.clone(),
+    Either::B(callback) => Arc::new(JsReplayGuard {
+      callback: ManuallyDrop::new(callback),
+      thread: std::thread::current().id(),
+    }),
+  }
+}
+
+#[derive(Default)]
+struct SeenJtis {
+  /// `jti` to the time after which it no longer needs to be remembered
+  expires_at: HashMap<String, u64>,
+  /// The same entries ordered by expiry, so that expired ones can be dropped from the front
+  by_expiry: BTreeSet<(u64, String)>,
+}
+
+impl SeenJtis {
+  fn forget_expired(&mut self, now: u64) {
+    let live = self.by_expiry.split_off(&(now, String::new()));
+    for (_, jti) in std::mem::replace(&mut self.by_expiry, live) {
+      self.expires_at.remove(&jti);
+    }
+  }
+}
+
+struct MemoryJtiStore {
+  max_capacity: usize,
+  /// Also makes the lookup and the insert one step, so concurrent verifies can't both accept a token
+  seen: Mutex<SeenJtis>,
+}
+
+impl ReplayGuard for MemoryJtiStore {
+  fn check_and_remember(
+    &self,
+    _env: Env,
+    jti: &str,
+    expires_at: u64,
+    now: u64,
+  ) -> Result<bool, Error> {
+    let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+
+    seen.forget_expired(now);
+    if seen.expires_at.contains_key(jti) {
+      return Ok(false);
+    }
+    // Forgetting a live id would let its token be replayed, so new ids are turned away instead
+    if seen.expires_at.len() >= self.max_capacity {
+      return Err(Error::Generic(format!(
+        "Replay guard is full with {} unexpired ids",
+        self.max_capacity
+      )));
+    }
+
+    seen.expires_at.insert(jti.to_owned(), expires_at);
+    seen.by_expiry.insert((expires_at, jti.to_owned()));
+    Ok(true)
+  }
+}
+
+/// Guard calling a JS function with the `jti` and the time until which it has to be remembered
+struct JsReplayGuard {
+  callback: ManuallyDrop<FunctionRef<(String, f64), bool>>,
+  /// The JS thread, the only one the reference may be released on
+  thread: ThreadId,
+}
+
+// SAFETY: the reference is only used with an `Env`, which only exists on the JS thread, and is
+// only released there
+unsafe impl Send for JsReplayGuard {}
+
+impl Drop for JsReplayGuard {
+  fn drop(&mut self) {
+    // Clients and tasks are dropped on the JS thread, anywhere else the reference is leaked
+    if std::thread::current().id() == self.thread {
+      // SAFETY: `callback` is not used after this
+      unsafe { ManuallyDrop::drop(&mut self.callback) };
+    }
+  }
+}
+
+impl ReplayGuard for JsReplayGuard {
+  fn check_and_remember(
+    &self,
+    env: Env,
+    jti: &str,
+    expires_at: u64,
+    _now: u64,
+  ) -> Result<bool, Error> {
+    self
+      .callback
+      .borrow_back(&env)
+      .and_then(|callback| callback.call((jti.to_owned(), expires_at as f64)))
+      .map_err(|e| Error::Generic(format!("Replay guard failed: {}", e.reason)))
+  }
+}
+
+/// Replay guard keeping seen `jti`s in memory until their tokens expire, for use with a single
+/// process
+#[napi]
+pub struct InMemoryReplayGuard {
+  store: Arc<MemoryJtiStore>,
+}
+
+#[napi]
+impl InMemoryReplayGuard {
+  #[napi(constructor)]
+  /// Remembers up to `maxCapacity` ids at a time. Ids are forgotten once their tokens expire, and
+  /// while `maxCapacity` ids are still remembered, tokens with new ids are rejected.
+  pub fn new(max_capacity: u32) -> Self {
+    Self {
+      store: Arc::new(MemoryJtiStore {
+        max_capacity: max_capacity as usize,
+        seen: Mutex::new(SeenJtis::default()),
+      }),
+    }
+  }
+}
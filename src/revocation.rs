@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLock};
+
+use crate::claims::Claims;
+use crate::errors::{Error, ErrorCode};
+
+/// Revoked tokens, ids and subjects. Tokens are kept until they expire, ids and subjects until
+/// the time given when revoking them, if any, or until they are lifted.
+///
+/// Unlike the token cache this is never trimmed to a capacity, since dropping an entry early
+/// would silently make a revoked token valid again.
+#[derive(Default)]
+pub(crate) struct Revocations {
+  /// Token to the time after which the revocation can be dropped
+  tokens: RwLock<HashMap<String, u64>>,
+  /// `jti` to the time after which the revocation can be dropped
+  jtis: RwLock<HashMap<String, u64>>,
+  /// `sub` to the `iat` before which its tokens are revoked, and the time after which the
+  /// revocation can be dropped
+  subjects: RwLock<HashMap<String, (u64, u64)>>,
+}
+
+/// Insert into `map`, dropping the entries that are no longer needed at `now`
+#[inline]
+fn insert_pruned<V>(
+  map: &RwLock<HashMap<String, V>>,
+  key: String,
+  value: V,
+  drop_after: impl Fn(&V) -> u64,
+  now: u64,
+) {
+  let mut map = map.write().unwrap_or_else(PoisonError::into_inner);
+
+  map.retain(|_, v| drop_after(v) >= now);
+  map.insert(key, value);
+}
+
+impl Revocations {
+  pub(crate) fn revoke_token(&self, token: String, drop_after: u64, now: u64) {
+    insert_pruned(&self.tokens, token, drop_after, |v| *v, now);
+  }
+
+  pub(crate) fn revoke_jti(&self, jti: String, drop_after: u64, now: u64) {
+    insert_pruned(&self.jtis, jti, drop_after, |v| *v, now);
+  }
+
+  pub(crate) fn revoke_subject(&self, sub: String, issued_before: u64, drop_after: u64, now: u64) {
+    insert_pruned(
+      &self.subjects,
+      sub,
+      (issued_before, drop_after),
+      |v| v.1,
+      now,
+    );
+  }
+
+  pub(crate) fn unrevoke_jti(&self, jti: &str) {
+    self
+      .jtis
+      .write()
+      .unwrap_or_else(PoisonError::into_inner)
+      .remove(jti);
+  }
+
+  pub(crate) fn unrevoke_subject(&self, sub: &str) {
+    self
+      .subjects
+      .write()
+      .unwrap_or_else(PoisonError::into_inner)
+      .remove(sub);
+  }
+
+  pub(crate) fn check(&self, token: &str, claims: &Claims) -> Result<(), Error> {
+    if self
+      .tokens
+      .read()
+      .unwrap_or_else(PoisonError::into_inner)
+      .contains_key(token)
+    {
+      return Err(Error::validation(
+        ErrorCode::Revoked,
+        "Token has been revoked",
+      ));
+    }
+
+    if let Some(jti) = &claims.jti {
+      if self
+        .jtis
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .contains_key(jti)
+      {
+        return Err(Error::invalid_claim(
+          ErrorCode::Revoked,
+          "jti",
+          format!("Tokens with jti `{jti}` have been revoked"),
+        ));
+      }
+    }
+
+    if let Some(sub) = &claims.sub {
+      if let Some((issued_before, _)) = self
+        .subjects
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(sub)
+      {
+        // Without `iat` there is no telling whether the token predates the revocation
+        let iat = claims
+          .iat
+          .as_ref()
+          .and_then(|iat| iat.as_u64())
+          .unwrap_or(0);
+        if iat < *issued_before {
+          return Err(Error::invalid_claim(
+            ErrorCode::Revoked,
+            "sub",
+            format!("Tokens for sub `{sub}` issued before {issued_before} have been revoked"),
+          ));
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
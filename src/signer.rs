@@ -0,0 +1,175 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{Map, Value};
+
+use crate::claims::{ClaimOpts, Claims, DefaultClaims};
+use crate::clock::Clock;
+use crate::errors::{Error, ErrorCode};
+use crate::keys::KeyFamily;
+
+/// `cty` of payloads signed as raw bytes, telling them apart from JSON when verifying
+pub(crate) const OCTET_STREAM: &str = "application/octet-stream";
+
+/// Whether a `cty` header is `OCTET_STREAM`, which may be written without `application/` (RFC 7515)
+#[inline]
+pub(crate) fn is_octet_stream(cty: Option<&str>) -> bool {
+  cty.is_some_and(|cty| {
+    cty.eq_ignore_ascii_case(OCTET_STREAM) || cty.eq_ignore_ascii_case("octet-stream")
+  })
+}
+
+/// Everything needed to turn claims into a token, shared by the clients and their tasks
+pub(crate) struct Signer {
+  pub(crate) header: jsonwebtoken::Header,
+  pub(crate) encoding_key: jsonwebtoken::EncodingKey,
+  /// Family of the algorithm `encoding_key` was built for
+  pub(crate) key_family: KeyFamily,
+  /// Put custom claims beside the registered ones instead of under `data`
+  pub(crate) flat_claims: bool,
+  pub(crate) default_claims: DefaultClaims,
+  pub(crate) clock: Clock,
+}
+
+#[inline]
+fn b64_encode_json<T: serde::Serialize>(value: &T) -> Result<String, Error> {
+  let json = serde_json::to_vec(value).map_err(|e| Error::Generic(e.to_string()))?;
+  Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+impl Signer {
+  /// Claims for a new token, with the client's default claims filled in
+  #[inline]
+  pub(crate) fn claims(
+    &self,
+    data: Map<String, Value>,
+    expires_in_seconds: u32,
+    claim_opts: Option<ClaimOpts>,
+  ) -> Claims {
+    let now = self.clock.now();
+
+    Claims::new_at(
+      now,
+      data,
+      expires_in_seconds,
+      Some(self.default_claims.apply(claim_opts, now)),
+    )
+  }
+
+  #[inline]
+  pub(crate) fn sign(&self, claims: &Claims) -> Result<String, Error> {
+    let token = if self.flat_claims {
+      jsonwebtoken::encode(&self.header, &claims.to_flat_payload()?, &self.encoding_key)
+    } else {
+      jsonwebtoken::encode(&self.header, claims, &self.encoding_key)
+    };
+
+    token.map_err(Error::from)
+  }
+
+  /// Checks once that the header's algorithm suits the key, as `jsonwebtoken::encode` does, and
+  /// returns a function signing messages with them
+  fn message_signer(&self) -> Result<impl Fn(&[u8]) -> Result<String, Error> + '_, Error> {
+    let alg = self.header.alg;
+    if KeyFamily::from(alg) != self.key_family {
+      return Err(Error::validation(
+        ErrorCode::InvalidAlgorithm,
+        "Header `alg` does not match the client's key",
+      ));
+    }
+
+    Ok(move |message: &[u8]| {
+      jsonwebtoken::crypto::sign(message, &self.encoding_key, alg).map_err(Error::from)
+    })
+  }
+
+  /// The client's header as JSON, for payloads that aren't a JWT, with `overrides` set over it
+  pub(crate) fn payload_header(
+    &self,
+    overrides: Map<String, Value>,
+  ) -> Result<Map<String, Value>, Error> {
+    let mut header: Map<String, Value> = serde_json::to_value(&self.header)
+      .and_then(serde_json::from_value)
+      .map_err(|e| Error::Generic(e.to_string()))?;
+    header.remove("typ");
+
+    if overrides
+      .get("alg")
+      .is_some_and(|alg| Some(alg) != header.get("alg"))
+    {
+      return Err(Error::validation(
+        ErrorCode::InvalidAlgorithm,
+        "Header `alg` does not match the client's algorithm",
+      ));
+    }
+    header.extend(overrides);
+
+    Ok(header)
+  }
+
+  /// JWS over `payload` as is (RFC 7797), returned without the payload as `header..signature`
+  pub(crate) fn sign_detached(&self, payload: &[u8]) -> Result<String, Error> {
+    let mut header = self.payload_header(Map::new())?;
+    header.insert("b64".to_owned(), Value::Bool(false));
+    header.insert("crit".to_owned(), Value::from(vec!["b64"]));
+
+    let encoded_header = b64_encode_json(&header)?;
+    let mut message = format!("{encoded_header}.").into_bytes();
+    message.extend(payload);
+    let signature = self.message_signer()?(&message)?;
+
+    Ok(format!("{encoded_header}..{signature}"))
+  }
+
+  /// Compact JWS over `payload` as is, without adding any claims to it. `content_type` is set as
+  /// the `cty` header if given.
+  pub(crate) fn sign_payload(
+    &self,
+    payload: &[u8],
+    content_type: Option<&str>,
+  ) -> Result<String, Error> {
+    let overrides = content_type
+      .map(|cty| Map::from_iter([("cty".to_owned(), Value::from(cty))]))
+      .unwrap_or_default();
+    let encoded_payload = URL_SAFE_NO_PAD.encode(payload);
+    let (encoded_header, signature) =
+      self.sign_json(&encoded_payload, &self.payload_header(overrides)?)?;
+
+    Ok(format!("{encoded_header}.{encoded_payload}.{signature}"))
+  }
+
+  /// Encoded protected header and signature of a JWS JSON serialization over `encoded_payload`
+  pub(crate) fn sign_json(
+    &self,
+    encoded_payload: &str,
+    protected: &Map<String, Value>,
+  ) -> Result<(String, String), Error> {
+    let encoded_header = b64_encode_json(protected)?;
+    let message = format!("{encoded_header}.{encoded_payload}");
+    let signature = self.message_signer()?(message.as_bytes())?;
+
+    Ok((encoded_header, signature))
+  }
+
+  /// Same as calling `sign` for each of the claims, but serializes the header only once
+  pub(crate) fn sign_many(
+    &self,
+    claims: impl Iterator<Item = Claims>,
+  ) -> Result<Vec<String>, Error> {
+    let sign_message = self.message_signer()?;
+    let encoded_header = b64_encode_json(&self.header)?;
+
+    claims
+      .map(|claims| {
+        let encoded_claims = if self.flat_claims {
+          b64_encode_json(&claims.to_flat_payload()?)?
+        } else {
+          b64_encode_json(&claims)?
+        };
+        let message = format!("{encoded_header}.{encoded_claims}");
+        let signature = sign_message(message.as_bytes())?;
+
+        Ok(format!("{message}.{signature}"))
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,137 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+
+use napi::{Env, Task};
+use napi_derive::napi;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::claims::{Claims, VerifiedClaims};
+use crate::errors::Error;
+use crate::jwt_client::VerifyResult;
+use crate::signer::Signer;
+use crate::verifier::{TokenCache, Verifier};
+
+/// Smallest batch worth handing to another thread of the pool
+const MIN_TOKENS_PER_THREAD: usize = 32;
+
+/// `compute` can only fail with a plain `napi::Error`, so the original error is kept aside for
+/// `reject` to throw with its code and claim
+#[inline]
+fn stash(slot: &mut Option<Error>, err: Error) -> napi::Error {
+  let placeholder = napi::Error::from_reason(err.message());
+  *slot = Some(err);
+  placeholder
+}
+
+#[inline]
+fn unstash(slot: &mut Option<Error>, env: Env, err: napi::Error) -> napi::Error {
+  match slot.take() {
+    Some(e) => e.into_js_error(env),
+    None => err,
+  }
+}
+
+pub struct SignTask {
+  pub(crate) signer: Arc<Signer>,
+  pub(crate) claims: Claims,
+  pub(crate) error: Option<Error>,
+}
+
+#[napi]
+impl Task for SignTask {
+  type Output = String;
+  type JsValue = String;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    self
+      .signer
+      .sign(&self.claims)
+      .map_err(|e| stash(&mut self.error, e))
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    Err(unstash(&mut self.error, env, err))
+  }
+}
+
+pub struct VerifyTask {
+  pub(crate) token: String,
+  pub(crate) verifier: Arc<Verifier>,
+  pub(crate) cache: Option<TokenCache>,
+  pub(crate) error: Option<Error>,
+}
+
+#[napi]
+impl Task for VerifyTask {
+  type Output = VerifiedClaims;
+  type JsValue = VerifiedClaims;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let token = std::mem::take(&mut self.token);
+    match &self.cache {
+      Some(cache) => self.verifier.verify_cached(cache, token),
+      None => self.verifier.verify(&token),
+    }
+    .and_then(|token_data| token_data.claims.into_verified(self.verifier.flat_claims))
+    .map_err(|e| stash(&mut self.error, e))
+  }
+
+  fn resolve(&mut self, env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    self
+      .verifier
+      .check_replay(env, &output)
+      .map_err(|e| e.into_js_error(env))?;
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    Err(unstash(&mut self.error, env, err))
+  }
+}
+
+pub struct VerifyManyTask {
+  pub(crate) tokens: Vec<String>,
+  pub(crate) verifier: Arc<Verifier>,
+  pub(crate) cache: Option<TokenCache>,
+  pub(crate) error: Option<Error>,
+}
+
+#[napi]
+impl Task for VerifyManyTask {
+  type Output = Vec<VerifyResult>;
+  type JsValue = Vec<VerifyResult>;
+
+  /// Spread over rayon's pool, bounded by the number of cores however many batches are pending
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let tokens = std::mem::take(&mut self.tokens);
+    let verifier = &self.verifier;
+    let cache = self.cache.as_ref();
+
+    // rayon resumes a panic of any of its threads here, which must not unwind into Node
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+      tokens
+        .into_par_iter()
+        .with_min_len(MIN_TOKENS_PER_THREAD)
+        .map(|token| verifier.verify_result(token, cache))
+        .collect()
+    }))
+    .map_err(|_| {
+      stash(
+        &mut self.error,
+        Error::Generic("Verification thread panicked".to_owned()),
+      )
+    })
+  }
+
+  fn resolve(&mut self, env: Env, mut output: Self::Output) -> napi::Result<Self::JsValue> {
+    self.verifier.check_replays(env, &mut output);
+    Ok(output)
+  }
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    Err(unstash(&mut self.error, env, err))
+  }
+}
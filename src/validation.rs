@@ -1,10 +1,14 @@
+use std::collections::BTreeMap;
+
 use napi_derive::napi;
-use serde_json::Number;
+use serde_json::{Number, Value};
 
 use crate::algorithm::Algorithm;
+use crate::claims::ClaimSet;
+use crate::errors::{Error, ErrorCode};
 
 #[napi(object)]
-#[derive(Default)]
+#[derive(Debug, Default, PartialEq, Eq, Hash)]
 pub struct Validation {
   /// If it contains a value, the validation will check that the `aud` field is a member of the
   /// audience provided and will error otherwise.
@@ -52,11 +56,373 @@ pub struct Validation {
   ///
   /// Defaults to `true`.
   pub validate_signature: Option<bool>,
+  /// Claims that must be present, registered or custom. Checked after the signature.
+  pub required_claims: Option<Vec<String>>,
+  /// Claims that must be equal to the given values
+  pub claim_equals: Option<BTreeMap<String, Value>>,
+  /// Claims that must be equal to one of the given values
+  pub claim_one_of: Option<BTreeMap<String, Vec<Value>>>,
+  /// Array claims that must contain all of the given values. A single value counts as an array
+  /// of one.
+  pub claim_contains: Option<BTreeMap<String, Vec<Value>>>,
+  /// Scopes that must be granted by the `scope` or `scp` claim
+  pub scopes: Option<ScopeRequirement>,
+  /// Roles that must be granted by the `roles` claim
+  pub roles: Option<ScopeRequirement>,
+  /// Maximum age of the token in seconds, measured from its `iat` claim, which it then must have.
+  /// Bounds how long a token is accepted regardless of its `exp`.
+  pub max_age: Option<u32>,
+  /// Whether to reject tokens with an `iat` claim in the future.
+  ///
+  /// Defaults to `false`.
+  pub validate_iat: Option<bool>,
+  /// Whether the `iat` claim must be present.
+  ///
+  /// Defaults to `false`.
+  pub require_iat: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScopeRequirement {
+  /// Every one of these must be granted
+  pub all_of: Option<Vec<String>>,
+  /// At least one of these must be granted
+  pub any_of: Option<Vec<String>>,
+}
+
+impl ScopeRequirement {
+  fn check(&self, granted: &[String], claim: &str) -> Result<(), Error> {
+    if let Some(missing) = self
+      .all_of
+      .iter()
+      .flatten()
+      .find(|required| !granted.contains(required))
+    {
+      return Err(Error::invalid_claim(
+        ErrorCode::InsufficientScope,
+        claim,
+        format!("`{missing}` is not granted by `{claim}`"),
+      ));
+    }
+
+    if let Some(any_of) = self.any_of.as_ref().filter(|any_of| !any_of.is_empty()) {
+      if !any_of.iter().any(|required| granted.contains(required)) {
+        return Err(Error::invalid_claim(
+          ErrorCode::InsufficientScope,
+          claim,
+          format!("None of {} is granted by `{claim}`", any_of.join(", ")),
+        ));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Checks on the decoded claims that `jsonwebtoken` has no notion of, run after the signature and
+/// registered claims were validated
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ClaimChecks {
+  required: Vec<String>,
+  equals: BTreeMap<String, Value>,
+  one_of: BTreeMap<String, Vec<Value>>,
+  contains: BTreeMap<String, Vec<Value>>,
+  scopes: Option<ScopeRequirement>,
+  roles: Option<ScopeRequirement>,
+  max_age: Option<u64>,
+  validate_iat: bool,
+  require_iat: bool,
+}
+
+impl ClaimChecks {
+  /// Replace the checks that are set in `validation`, keeping the rest
+  pub(crate) fn layer(&mut self, validation: &Validation) {
+    if let Some(required) = &validation.required_claims {
+      self.required.clone_from(required);
+    }
+    if let Some(equals) = &validation.claim_equals {
+      self.equals.clone_from(equals);
+    }
+    if let Some(one_of) = &validation.claim_one_of {
+      self.one_of.clone_from(one_of);
+    }
+    if let Some(contains) = &validation.claim_contains {
+      self.contains.clone_from(contains);
+    }
+    if validation.scopes.is_some() {
+      self.scopes.clone_from(&validation.scopes);
+    }
+    if validation.roles.is_some() {
+      self.roles.clone_from(&validation.roles);
+    }
+    if let Some(max_age) = validation.max_age {
+      self.max_age = Some(u64::from(max_age));
+    }
+    if let Some(validate_iat) = validation.validate_iat {
+      self.validate_iat = validate_iat;
+    }
+    if let Some(require_iat) = validation.require_iat {
+      self.require_iat = require_iat;
+    }
+  }
+
+  pub(crate) fn check(&self, claims: &impl ClaimSet) -> Result<(), Error> {
+    let required = self
+      .required
+      .iter()
+      .chain(self.equals.keys())
+      .chain(self.one_of.keys())
+      .chain(self.contains.keys());
+    for name in required {
+      if claims.claim(name).is_none() {
+        return Err(Error::invalid_claim(
+          ErrorCode::MissingClaim,
+          name,
+          format!("Missing required claim `{name}`"),
+        ));
+      }
+    }
+
+    for (name, expected) in &self.equals {
+      if claims.claim(name).as_ref() != Some(expected) {
+        return Err(Error::invalid_claim(
+          ErrorCode::InvalidClaim,
+          name,
+          format!("Claim `{name}` does not have the expected value"),
+        ));
+      }
+    }
+
+    for (name, allowed) in &self.one_of {
+      let value = claims.claim(name);
+      if !allowed.iter().any(|v| value.as_ref() == Some(v)) {
+        return Err(Error::invalid_claim(
+          ErrorCode::InvalidClaim,
+          name,
+          format!("Claim `{name}` is not one of the allowed values"),
+        ));
+      }
+    }
+
+    for (name, expected) in &self.contains {
+      let value = claims.claim(name);
+      let values = match &value {
+        Some(Value::Array(values)) => values.as_slice(),
+        Some(single) => std::slice::from_ref(single),
+        None => &[],
+      };
+      if let Some(missing) = expected.iter().find(|v| !values.contains(v)) {
+        return Err(Error::invalid_claim(
+          ErrorCode::InvalidClaim,
+          name,
+          format!("Claim `{name}` does not contain {missing}"),
+        ));
+      }
+    }
+
+    if let Some(scopes) = &self.scopes {
+      let claim = if claims.claim("scp").is_some() && claims.claim("scope").is_none() {
+        "scp"
+      } else {
+        "scope"
+      };
+      scopes.check(&claims.scopes(), claim)?;
+    }
+    if let Some(roles) = &self.roles {
+      roles.check(&claims.roles(), "roles")?;
+    }
+
+    Ok(())
+  }
+
+  /// Checks on `iat` against the current time, with the same `leeway` as `exp` and `nbf`. An
+  /// `iat` that is not a number counts as missing.
+  ///
+  /// Unlike the other checks these can start failing for a token that passed before, so they
+  /// are run on cache hits as well.
+  pub(crate) fn check_iat(
+    &self,
+    claims: &impl ClaimSet,
+    now: u64,
+    leeway: u64,
+  ) -> Result<(), Error> {
+    let Some(Value::Number(iat)) = claims.claim("iat") else {
+      if self.require_iat || self.max_age.is_some() {
+        return Err(Error::invalid_claim(
+          ErrorCode::MissingClaim,
+          "iat",
+          "Missing `iat` claim",
+        ));
+      }
+      return Ok(());
+    };
+    if !self.validate_iat && self.max_age.is_none() {
+      return Ok(());
+    }
+
+    let iat = iat.as_u64().ok_or_else(|| {
+      Error::invalid_claim(
+        ErrorCode::InvalidClaim,
+        "iat",
+        "Claim `iat` is not a valid timestamp",
+      )
+    })?;
+    if self.validate_iat && iat > now.saturating_add(leeway) {
+      return Err(Error::invalid_claim(
+        ErrorCode::NotYetValid,
+        "iat",
+        "Token was issued in the future",
+      ));
+    }
+
+    if let Some(max_age) = self.max_age {
+      if iat.saturating_add(max_age).saturating_add(leeway) < now {
+        return Err(Error::invalid_claim(
+          ErrorCode::Expired,
+          "iat",
+          format!("Token is older than {max_age} seconds"),
+        ));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// A `NumericDate` in whole seconds, with fractions rounded as `jsonwebtoken` does. `None` unless
+/// it is a number that fits a `u64`.
+pub(crate) fn numeric_date(value: &Number) -> Option<u64> {
+  value.as_u64().or_else(|| {
+    value
+      .as_f64()
+      .filter(|secs| secs.is_finite() && *secs >= 0.0 && *secs < u64::MAX as f64)
+      .map(|secs| secs.round() as u64)
+  })
+}
+
+/// The `exp` and `nbf` checks `jsonwebtoken` does, against `now` instead of the system time.
+/// Either claim is only checked if it is a number, rounded to whole seconds, and an `exp` that is
+/// negative or too large counts as expired.
+pub(crate) fn check_times(
+  claims: &impl ClaimSet,
+  validation: &jsonwebtoken::Validation,
+  now: u64,
+) -> Result<(), Error> {
+  use jsonwebtoken::errors::ErrorKind;
+
+  if let (true, Some(Value::Number(exp))) = (validation.validate_exp, claims.claim("exp")) {
+    let exp = numeric_date(&exp).unwrap_or_default();
+    if exp.saturating_sub(validation.reject_tokens_expiring_in_less_than)
+      < now.saturating_sub(validation.leeway)
+    {
+      return Err(jsonwebtoken::errors::Error::from(ErrorKind::ExpiredSignature).into());
+    }
+  }
+
+  let nbf = match claims.claim("nbf") {
+    Some(Value::Number(nbf)) => numeric_date(&nbf),
+    _ => None,
+  };
+  if let (true, Some(nbf)) = (validation.validate_nbf, nbf) {
+    if nbf > now.saturating_add(validation.leeway) {
+      return Err(jsonwebtoken::errors::Error::from(ErrorKind::ImmatureSignature).into());
+    }
+  }
+
+  Ok(())
+}
+
+/// Whether `value` has the type RFC 7519 gives the registered claim `name`
+fn has_registered_type(name: &str, value: &Value) -> bool {
+  match name {
+    "exp" | "nbf" | "iat" => value.is_number(),
+    "aud" => {
+      value.is_string()
+        || value
+          .as_array()
+          .is_some_and(|aud| aud.iter().all(Value::is_string))
+    }
+    _ => value.is_string(),
+  }
+}
+
+/// Every check `jsonwebtoken` does on the registered claims, for payloads it did not decode
+/// itself. Registered claims that don't have their registered type are treated as custom claims,
+/// so they are neither required nor validated, except that they never match an expected `sub`,
+/// `iss` or `aud`.
+pub(crate) fn check_registered(
+  claims: &impl ClaimSet,
+  validation: &jsonwebtoken::Validation,
+  now: u64,
+) -> Result<(), Error> {
+  use jsonwebtoken::errors::ErrorKind;
+
+  for required in &validation.required_spec_claims {
+    let present = claims
+      .claim(required)
+      .is_some_and(|value| has_registered_type(required, &value));
+    if !present {
+      return Err(
+        jsonwebtoken::errors::Error::from(ErrorKind::MissingRequiredClaim(required.clone())).into(),
+      );
+    }
+  }
+
+  check_times(claims, validation, now)?;
+
+  if let (Some(sub), Some(expected)) = (claims.claim("sub"), &validation.sub) {
+    if sub.as_str() != Some(expected) {
+      return Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidSubject).into());
+    }
+  }
+
+  if let (Some(iss), Some(expected)) = (claims.claim("iss"), &validation.iss) {
+    if !iss.as_str().is_some_and(|iss| expected.contains(iss)) {
+      return Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidIssuer).into());
+    }
+  }
+
+  if validation.validate_aud {
+    let matches = match (claims.claim("aud"), &validation.aud) {
+      (None, _) => true,
+      (Some(_), None) => false,
+      (Some(Value::String(aud)), Some(expected)) => expected.contains(&aud),
+      (Some(Value::Array(aud)), Some(expected)) => aud
+        .iter()
+        .filter_map(Value::as_str)
+        .any(|aud| expected.contains(aud)),
+      (Some(_), Some(_)) => false,
+    };
+    if !matches {
+      return Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidAudience).into());
+    }
+  }
+
+  Ok(())
 }
 
 impl Validation {
+  /// Split into the validation done by `jsonwebtoken` and the claim checks done afterwards
+  pub(crate) fn into_parts(
+    self,
+    alg: jsonwebtoken::Algorithm,
+  ) -> (jsonwebtoken::Validation, ClaimChecks) {
+    let mut claim_checks = ClaimChecks::default();
+    claim_checks.layer(&self);
+
+    (self.for_jsonwebtoken(alg), claim_checks)
+  }
+
   pub fn for_jsonwebtoken(self, alg: jsonwebtoken::Algorithm) -> jsonwebtoken::Validation {
     let mut validation = jsonwebtoken::Validation::new(alg);
+    self.apply_to(&mut validation);
+
+    validation
+  }
+
+  /// Override the fields of `validation` that are set here, leaving the rest as they are
+  pub fn apply_to(&self, validation: &mut jsonwebtoken::Validation) {
     if let Some(aud) = &self.aud {
       validation.set_audience(aud);
     }
@@ -65,7 +431,7 @@ impl Validation {
       validation.set_required_spec_claims(required_spec_claims);
     }
 
-    if let Some(leeway) = self.leeway.and_then(|l| l.as_u64()) {
+    if let Some(leeway) = self.leeway.as_ref().and_then(Number::as_u64) {
       validation.leeway = leeway;
     }
 
@@ -77,7 +443,9 @@ impl Validation {
       validation.validate_nbf = validate_nbf;
     }
 
-    validation.sub = self.sub;
+    if let Some(sub) = &self.sub {
+      validation.sub = Some(sub.clone());
+    }
 
     if let Some(algorithms) = &self.algorithms {
       validation.algorithms = algorithms.iter().map(|alg| alg.to_owned().into()).collect();
@@ -90,7 +458,5 @@ impl Validation {
     if let Some(false) = self.validate_signature {
       validation.insecure_disable_signature_validation()
     }
-
-    validation
   }
 }
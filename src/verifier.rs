@@ -0,0 +1,485 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::TokenData;
+use napi::Env;
+use serde_json::{Map, Value};
+
+use crate::claims::{ceil_secs, ClaimSet, Claims};
+use crate::clock::Clock;
+use crate::errors::{Error, ErrorCode};
+use crate::jwt_client::VerifyResult;
+use crate::keys::KeyFamily;
+use crate::replay::ReplayGuard;
+use crate::revocation::Revocations;
+use crate::validation::{check_registered, check_times, ClaimChecks, Validation};
+
+pub(crate) type TokenCache = mini_moka::sync::Cache<CacheKey, CachedToken>;
+
+/// A verified token, with the end of the grace period of the previous key that verified it, so
+/// that it is not accepted from the cache for any longer than that key
+#[derive(Clone)]
+pub(crate) struct CachedToken {
+  pub(crate) token_data: TokenData<Claims>,
+  key_valid_until: Option<u64>,
+}
+
+/// A token, and the per-call validation it was verified with if any, so that results cached under
+/// one validation are never returned for another
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+  pub(crate) token: String,
+  scope: Option<Arc<Validation>>,
+}
+
+impl CacheKey {
+  /// Key for a token verified with the client's own validation
+  #[inline]
+  pub(crate) fn unscoped(token: String) -> Self {
+    Self { token, scope: None }
+  }
+}
+
+/// Decoding key replaced by a rotation, still accepted until `valid_until`
+#[derive(Clone)]
+pub(crate) struct PreviousKey {
+  pub(crate) key_id: Option<String>,
+  pub(crate) decoding_key: Arc<jsonwebtoken::DecodingKey>,
+  pub(crate) valid_until: u64,
+}
+
+/// Everything needed to check a token and decode its claims, shared by the clients and their tasks
+#[derive(Clone)]
+pub(crate) struct Verifier {
+  /// Shared with the verifiers layered over this one, as are the previous keys
+  pub(crate) decoding_key: Arc<jsonwebtoken::DecodingKey>,
+  /// Family of the current and previous keys, which every allowed algorithm must belong to
+  pub(crate) key_family: KeyFamily,
+  /// `kid` of the current key, used to pick it over the previous ones
+  pub(crate) key_id: Option<String>,
+  pub(crate) previous_keys: Arc<[PreviousKey]>,
+  pub(crate) validation: jsonwebtoken::Validation,
+  pub(crate) claim_checks: ClaimChecks,
+  /// Expect custom claims beside the registered ones instead of under `data`
+  pub(crate) flat_claims: bool,
+  /// If set, every token must carry a `jti` that has not been seen before
+  pub(crate) replay_guard: Option<Arc<dyn ReplayGuard>>,
+  /// Checked after decoding, so a revoked token fails even if it is still cached
+  pub(crate) revocations: Option<Arc<Revocations>>,
+  /// Set when the validation was overridden per call, so that its cached results are kept apart
+  pub(crate) cache_scope: Option<Arc<Validation>>,
+  /// If set, it is used instead of the system time for every time based check: `exp`, `nbf`,
+  /// `iat`, replays, revocations and the grace period of previous keys
+  pub(crate) clock: Option<Clock>,
+}
+
+impl Verifier {
+  /// This verifier with the fields set in `validation` layered over its own validation. The keys
+  /// are shared with this verifier rather than copied.
+  pub(crate) fn layered(self: &Arc<Self>, validation: Option<Validation>) -> Arc<Self> {
+    let Some(validation) = validation else {
+      return self.clone();
+    };
+
+    let mut verifier = self.as_ref().clone();
+    verifier.claim_checks.layer(&validation);
+    validation.apply_to(&mut verifier.validation);
+    verifier.cache_scope = Some(Arc::new(validation));
+
+    Arc::new(verifier)
+  }
+
+  /// Current key, followed by the previous keys still within their grace period, each with the
+  /// `kid` and the end of the grace period of previous keys
+  pub(crate) fn keys(
+    &self,
+  ) -> impl Iterator<Item = (Option<&str>, &jsonwebtoken::DecodingKey, Option<u64>)> {
+    let now = self.now();
+    let previous = self
+      .previous_keys
+      .iter()
+      .filter(move |key| key.valid_until >= now)
+      .map(|key| {
+        (
+          key.key_id.as_deref(),
+          key.decoding_key.as_ref(),
+          Some(key.valid_until),
+        )
+      });
+
+    std::iter::once((self.key_id.as_deref(), self.decoding_key.as_ref(), None)).chain(previous)
+  }
+
+  pub(crate) fn now(&self) -> u64 {
+    self
+      .clock
+      .as_ref()
+      .map_or_else(jsonwebtoken::get_current_timestamp, Clock::now)
+  }
+
+  #[inline]
+  fn decode_with(
+    &self,
+    token: &str,
+    decoding_key: &jsonwebtoken::DecodingKey,
+  ) -> Result<TokenData<Claims>, Error> {
+    let Some(clock) = &self.clock else {
+      return self.decode_claims(token, decoding_key, &self.validation);
+    };
+
+    // `jsonwebtoken` only knows the system time, so the times are checked here instead
+    let mut validation = self.validation.clone();
+    validation.validate_exp = false;
+    validation.validate_nbf = false;
+    let token_data = self.decode_claims(token, decoding_key, &validation)?;
+    check_times(&token_data.claims, &self.validation, clock.now())?;
+
+    Ok(token_data)
+  }
+
+  #[inline]
+  fn decode_claims(
+    &self,
+    token: &str,
+    decoding_key: &jsonwebtoken::DecodingKey,
+    validation: &jsonwebtoken::Validation,
+  ) -> Result<TokenData<Claims>, Error> {
+    if !self.flat_claims {
+      return jsonwebtoken::decode::<Claims>(token, decoding_key, validation).map_err(Error::from);
+    }
+
+    let token_data = jsonwebtoken::decode::<Map<String, Value>>(token, decoding_key, validation)?;
+
+    Ok(TokenData {
+      header: token_data.header,
+      claims: Claims::from_flat_payload(token_data.claims)?,
+    })
+  }
+
+  /// Keys with the token's `kid` if there are any, otherwise every key in order, each with the
+  /// end of its grace period if it is a previous key
+  fn keys_for<'a>(
+    &'a self,
+    kid: Option<&'a str>,
+  ) -> impl Iterator<Item = (&'a jsonwebtoken::DecodingKey, Option<u64>)> {
+    let matches_kid = move |key_id: Option<&str>| kid.is_some() && key_id == kid;
+    let by_kid = self.keys().any(|(key_id, ..)| matches_kid(key_id));
+
+    self
+      .keys()
+      .filter(move |(key_id, ..)| !by_kid || matches_kid(*key_id))
+      .map(|(_, decoding_key, valid_until)| (decoding_key, valid_until))
+  }
+
+  /// Tries the keys for the token's `kid` in order until one of them matches the signature, and
+  /// returns the end of the grace period of the matching key if it is a previous key
+  fn decode(&self, token: &str) -> Result<(TokenData<Claims>, Option<u64>), Error> {
+    if self.previous_keys.is_empty() {
+      return Ok((self.decode_with(token, &self.decoding_key)?, None));
+    }
+
+    let kid = jsonwebtoken::decode_header(token)?.kid;
+
+    let mut result = Err(Error::validation(
+      ErrorCode::InvalidSignature,
+      "No key matches the token",
+    ));
+    for (decoding_key, valid_until) in self.keys_for(kid.as_deref()) {
+      result = self
+        .decode_with(token, decoding_key)
+        .map(|token_data| (token_data, valid_until));
+      if !matches!(&result, Err(e) if e.code() == ErrorCode::InvalidSignature) {
+        break;
+      }
+    }
+
+    result
+  }
+
+  /// Runs on every verification, including cache hits, which may have expired since they were
+  /// cached
+  fn check_state(&self, token: &str, claims: &Claims) -> Result<(), Error> {
+    let now = self.now();
+    check_times(claims, &self.validation, now)?;
+    self
+      .claim_checks
+      .check_iat(claims, now, self.validation.leeway)?;
+
+    if let Some(revocations) = &self.revocations {
+      revocations.check(token, claims)?;
+    }
+
+    Ok(())
+  }
+
+  /// Accept each `jti` only once, if there is a replay guard. Run after every other check,
+  /// including on cache hits, and on the JS thread since the guard may call into JS.
+  pub(crate) fn check_replay(&self, env: Env, claims: &impl ClaimSet) -> Result<(), Error> {
+    let Some(guard) = &self.replay_guard else {
+      return Ok(());
+    };
+    let jti = match claims.claim("jti") {
+      Some(Value::String(jti)) => jti,
+      Some(_) => {
+        return Err(Error::invalid_claim(
+          ErrorCode::InvalidClaim,
+          "jti",
+          "Claim `jti` is not a string",
+        ))
+      }
+      None => {
+        return Err(Error::invalid_claim(
+          ErrorCode::MissingClaim,
+          "jti",
+          "Missing `jti` claim",
+        ))
+      }
+    };
+    // Payloads without `exp` never expire, so neither may their `jti` be forgotten
+    let expires_at = match claims.claim("exp") {
+      Some(Value::Number(exp)) => ceil_secs(&exp).saturating_add(self.validation.leeway),
+      _ => u64::MAX,
+    };
+
+    if !guard.check_and_remember(env, &jti, expires_at, self.now())? {
+      return Err(Error::invalid_claim(
+        ErrorCode::Replayed,
+        "jti",
+        format!("Token with jti `{jti}` was already used"),
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// `check_replay` for each verified token of a batch, in order
+  pub(crate) fn check_replays(&self, env: Env, results: &mut [VerifyResult]) {
+    for result in results {
+      let Some(claims) = &result.claims else {
+        continue;
+      };
+      if let Err(e) = self.check_replay(env, claims) {
+        *result = Err(e).into();
+      }
+    }
+  }
+
+  /// Checks the signature and `exp` only, so that tokens accepted under some per-call validation
+  /// are accepted here whatever their other claims
+  pub(crate) fn verify_authentic(&self, token: &str) -> Result<TokenData<Claims>, Error> {
+    let mut verifier = self.clone();
+    let validation = &mut verifier.validation;
+    validation.validate_aud = false;
+    validation.validate_nbf = false;
+    validation.iss = None;
+    validation.sub = None;
+    validation.required_spec_claims = HashSet::from(["exp".to_owned()]);
+
+    Ok(verifier.decode(token)?.0)
+  }
+
+  #[inline]
+  fn verify_keyed(&self, token: &str) -> Result<(TokenData<Claims>, Option<u64>), Error> {
+    let (token_data, key_valid_until) = self.decode(token)?;
+    self.claim_checks.check(&token_data.claims)?;
+    self.check_state(token, &token_data.claims)?;
+
+    Ok((token_data, key_valid_until))
+  }
+
+  #[inline]
+  pub(crate) fn verify(&self, token: &str) -> Result<TokenData<Claims>, Error> {
+    Ok(self.verify_keyed(token)?.0)
+  }
+
+  /// Verify a JWS with a detached payload (`header..signature`) against `payload`. The payload is
+  /// used as is if the header has `b64: false` (RFC 7797), and base64url encoded otherwise.
+  pub(crate) fn verify_detached(
+    &self,
+    jws: &str,
+    payload: &[u8],
+  ) -> Result<jsonwebtoken::Header, Error> {
+    let malformed = |msg: &str| Error::validation(ErrorCode::Malformed, msg);
+
+    let mut parts = jws.split('.');
+    let (Some(encoded_header), Some(""), Some(signature), None) =
+      (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+      return Err(malformed(
+        "Expected a JWS with a detached payload, as `header..signature`",
+      ));
+    };
+    let header: Map<String, Value> = URL_SAFE_NO_PAD
+      .decode(encoded_header)
+      .ok()
+      .and_then(|json| serde_json::from_slice(&json).ok())
+      .ok_or_else(|| malformed("Header is not base64url encoded JSON"))?;
+
+    let b64 = match header.get("b64") {
+      None => true,
+      Some(Value::Bool(b64)) => *b64,
+      Some(_) => return Err(malformed("`b64` header must be a boolean")),
+    };
+    let crit = match header.get("crit") {
+      None => vec![],
+      Some(Value::Array(crit)) => crit
+        .iter()
+        .map(Value::as_str)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| malformed("`crit` header must be an array of strings"))?,
+      Some(_) => return Err(malformed("`crit` header must be an array of strings")),
+    };
+    if let Some(unknown) = crit.iter().find(|name| **name != "b64") {
+      return Err(malformed(&format!(
+        "Unsupported critical header `{unknown}`"
+      )));
+    }
+    if !b64 && !crit.contains(&"b64") {
+      return Err(malformed("`b64` header must be listed in `crit`"));
+    }
+
+    let header: jsonwebtoken::Header =
+      serde_json::from_value(Value::Object(header)).map_err(|e| malformed(&e.to_string()))?;
+
+    let mut message = format!("{encoded_header}.").into_bytes();
+    if b64 {
+      message.extend(URL_SAFE_NO_PAD.encode(payload).into_bytes());
+    } else {
+      message.extend(payload);
+    }
+
+    self.verify_signature(&header, &message, signature)?;
+
+    Ok(header)
+  }
+
+  /// Verify a compact JWS over any payload, returning its header and decoded payload. The
+  /// registered claims of a JSON object payload are only validated if they are present with their
+  /// registered type, and the claim checks are run against the object, or against no claims for
+  /// any other payload.
+  pub(crate) fn verify_payload(
+    &self,
+    token: &str,
+  ) -> Result<(jsonwebtoken::Header, Vec<u8>), Error> {
+    let malformed = |msg: &str| Error::validation(ErrorCode::Malformed, msg);
+
+    let mut parts = token.split('.');
+    let (Some(encoded_header), Some(encoded_payload), Some(signature), None) =
+      (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+      return Err(malformed(
+        "Expected a compact JWS, as `header.payload.signature`",
+      ));
+    };
+    let header = jsonwebtoken::decode_header(token)?;
+    let message = format!("{encoded_header}.{encoded_payload}");
+    self.verify_signature(&header, message.as_bytes(), signature)?;
+
+    let payload = URL_SAFE_NO_PAD
+      .decode(encoded_payload)
+      .map_err(|_| malformed("Payload is not base64url encoded"))?;
+    let claims = match serde_json::from_slice(&payload) {
+      Ok(Value::Object(claims)) => claims,
+      _ => Map::new(),
+    };
+
+    let mut validation = self.validation.clone();
+    validation.required_spec_claims.clear();
+
+    let now = self.now();
+    check_registered(&claims, &validation, now)?;
+    self.claim_checks.check(&claims)?;
+    self
+      .claim_checks
+      .check_iat(&claims, now, validation.leeway)?;
+
+    Ok((header, payload))
+  }
+
+  /// Check the signature of a JWS signing input against the keys for the header's `kid`. As with
+  /// `jsonwebtoken::decode`, every allowed algorithm must match the family of the keys, so that
+  /// e.g. an RSA public key is never used as an HMAC secret.
+  pub(crate) fn verify_signature(
+    &self,
+    header: &jsonwebtoken::Header,
+    message: &[u8],
+    signature: &str,
+  ) -> Result<(), Error> {
+    let family_mismatch = self
+      .validation
+      .algorithms
+      .iter()
+      .any(|alg| KeyFamily::from(*alg) != self.key_family);
+    if family_mismatch || !self.validation.algorithms.contains(&header.alg) {
+      return Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidAlgorithm).into());
+    }
+
+    for (decoding_key, _) in self.keys_for(header.kid.as_deref()) {
+      if jsonwebtoken::crypto::verify(signature, message, decoding_key, header.alg)? {
+        return Ok(());
+      }
+    }
+
+    Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidSignature).into())
+  }
+
+  #[inline]
+  pub(crate) fn verify_cached(
+    &self,
+    cache: &TokenCache,
+    token: String,
+  ) -> Result<TokenData<Claims>, Error> {
+    let key = CacheKey {
+      token,
+      scope: self.cache_scope.clone(),
+    };
+
+    // Once the grace period of the key that verified it is over, the token is verified again, and
+    // fails unless it matches one of the keys still accepted
+    let now = self.now();
+    let cached = cache
+      .get(&key)
+      .filter(|cached| cached.key_valid_until.is_none_or(|until| until >= now));
+    if let Some(cached) = cached {
+      self.check_state(&key.token, &cached.token_data.claims)?;
+      return Ok(cached.token_data);
+    }
+
+    let (token_data, key_valid_until) = self.verify_keyed(&key.token)?;
+    cache.insert(
+      key,
+      CachedToken {
+        token_data: token_data.clone(),
+        key_valid_until,
+      },
+    );
+
+    Ok(token_data)
+  }
+
+  /// Verify through `cache` if given, keeping the outcome as a result of a batch
+  #[inline]
+  pub(crate) fn verify_result(&self, token: String, cache: Option<&TokenCache>) -> VerifyResult {
+    match cache {
+      Some(cache) => self.verify_cached(cache, token),
+      None => self.verify(&token),
+    }
+    .and_then(|td| td.claims.into_verified(self.flat_claims))
+    .into()
+  }
+
+  #[inline]
+  pub(crate) fn verify_many(
+    &self,
+    tokens: Vec<String>,
+    cache: Option<&TokenCache>,
+  ) -> Vec<VerifyResult> {
+    tokens
+      .into_iter()
+      .map(|token| self.verify_result(token, cache))
+      .collect()
+  }
+}